@@ -4,6 +4,7 @@ use std::fs;
 use std::hash::Hash;
 use std::io::Error;
 use std::io::Write;
+use std::thread;
 
 pub fn run() -> Result<(), Error> {
     let _ = task1();
@@ -21,6 +22,20 @@ enum Direction {
     Down,
     Left,
 }
+impl Direction {
+    fn turn_right(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 struct Location {
@@ -28,7 +43,7 @@ struct Location {
     col: usize,
 }
 
-#[derive(PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 struct GuardState {
     location: Location,
     direction: Direction,
@@ -90,6 +105,138 @@ impl Map {
     }
 }
 
+/// For every free cell and direction, the cell the guard ends up on when walking straight until
+/// the next barrier, or None if the guard leaves the map before hitting one.
+struct JumpTable {
+    stops: [Vec<Option<Location>>; 4],
+    width: usize,
+}
+impl JumpTable {
+    pub fn from_map(map: &Map) -> JumpTable {
+        let mut stops: [Vec<Option<Location>>; 4] =
+            std::array::from_fn(|_| vec![None; map.height * map.width]);
+
+        for col in 0..map.width {
+            let mut last_stop = None;
+            for row in 0..map.height {
+                if map.barriers.contains(&Location { row, col }) {
+                    last_stop = Some(Location { row: row + 1, col });
+                } else {
+                    stops[Direction::Up.index()][row * map.width + col] = last_stop;
+                }
+            }
+            let mut last_stop = None;
+            for row in (0..map.height).rev() {
+                if map.barriers.contains(&Location { row, col }) {
+                    last_stop = row.checked_sub(1).map(|row| Location { row, col });
+                } else {
+                    stops[Direction::Down.index()][row * map.width + col] = last_stop;
+                }
+            }
+        }
+        for row in 0..map.height {
+            let mut last_stop = None;
+            for col in 0..map.width {
+                if map.barriers.contains(&Location { row, col }) {
+                    last_stop = Some(Location { row, col: col + 1 });
+                } else {
+                    stops[Direction::Left.index()][row * map.width + col] = last_stop;
+                }
+            }
+            let mut last_stop = None;
+            for col in (0..map.width).rev() {
+                if map.barriers.contains(&Location { row, col }) {
+                    last_stop = col.checked_sub(1).map(|col| Location { row, col });
+                } else {
+                    stops[Direction::Right.index()][row * map.width + col] = last_stop;
+                }
+            }
+        }
+
+        JumpTable {
+            stops,
+            width: map.width,
+        }
+    }
+
+    /// Where the guard stops when walking from `location` in `direction`, taking into account one
+    /// extra obstacle that is not part of the table.
+    fn next_stop(
+        &self,
+        location: Location,
+        direction: Direction,
+        extra_obstacle: Location,
+    ) -> Option<Location> {
+        let stop = self.stops[direction.index()][location.row * self.width + location.col];
+
+        // the extra obstacle only matters if it lies between the guard and the regular stop
+        let blocked_by_extra = match direction {
+            Direction::Up => {
+                extra_obstacle.col == location.col
+                    && extra_obstacle.row < location.row
+                    && stop.is_none_or(|stop| extra_obstacle.row >= stop.row)
+            }
+            Direction::Down => {
+                extra_obstacle.col == location.col
+                    && extra_obstacle.row > location.row
+                    && stop.is_none_or(|stop| extra_obstacle.row <= stop.row)
+            }
+            Direction::Left => {
+                extra_obstacle.row == location.row
+                    && extra_obstacle.col < location.col
+                    && stop.is_none_or(|stop| extra_obstacle.col >= stop.col)
+            }
+            Direction::Right => {
+                extra_obstacle.row == location.row
+                    && extra_obstacle.col > location.col
+                    && stop.is_none_or(|stop| extra_obstacle.col <= stop.col)
+            }
+        };
+        if !blocked_by_extra {
+            return stop;
+        }
+
+        match direction {
+            Direction::Up => Some(Location {
+                row: extra_obstacle.row + 1,
+                col: location.col,
+            }),
+            Direction::Down => Some(Location {
+                row: extra_obstacle.row - 1,
+                col: location.col,
+            }),
+            Direction::Left => Some(Location {
+                row: location.row,
+                col: extra_obstacle.col + 1,
+            }),
+            Direction::Right => Some(Location {
+                row: location.row,
+                col: extra_obstacle.col - 1,
+            }),
+        }
+    }
+
+    /// Moves the guard from obstacle to obstacle and checks whether it ends up in a loop.
+    fn has_loop(&self, start_state: GuardState, extra_obstacle: Location) -> bool {
+        let mut guard_state = start_state;
+        let mut turning_states: HashSet<GuardState> = HashSet::new();
+
+        while let Some(stop) =
+            self.next_stop(guard_state.location, guard_state.direction, extra_obstacle)
+        {
+            guard_state = GuardState {
+                location: stop,
+                direction: guard_state.direction.turn_right(),
+            };
+            if !turning_states.insert(guard_state) {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
 fn walk_guard(
     map: &Map,
     mut guard_location: Location,
@@ -167,6 +314,93 @@ fn walk_guard(
     (has_loop, visited_locations)
 }
 
+/// Walks the original path and returns, for every cell the guard visits (apart from the starting
+/// cell), the guard state right before it first steps onto that cell. Placing an obstacle there
+/// cannot change the path up to that state, so a simulation can start from it. Stops when the
+/// original path loops, since all of its cells have been visited by then.
+fn obstacle_candidates(
+    map: &Map,
+    mut guard_location: Location,
+    mut guard_direction: Direction,
+) -> Vec<(Location, GuardState)> {
+    let mut visited_locations: HashSet<Location> = HashSet::from([guard_location]);
+    let mut visited_states: HashSet<GuardState> = HashSet::new();
+    let mut candidates = Vec::new();
+
+    while visited_states.insert(GuardState {
+        location: guard_location,
+        direction: guard_direction,
+    }) {
+        let next_location = match guard_direction {
+            Direction::Up if guard_location.row > 0 => Location {
+                row: guard_location.row - 1,
+                col: guard_location.col,
+            },
+            Direction::Down if guard_location.row < map.height - 1 => Location {
+                row: guard_location.row + 1,
+                col: guard_location.col,
+            },
+            Direction::Left if guard_location.col > 0 => Location {
+                row: guard_location.row,
+                col: guard_location.col - 1,
+            },
+            Direction::Right if guard_location.col < map.width - 1 => Location {
+                row: guard_location.row,
+                col: guard_location.col + 1,
+            },
+            _ => break,
+        };
+
+        if map.barriers.contains(&next_location) {
+            guard_direction = guard_direction.turn_right();
+            continue;
+        }
+        if visited_locations.insert(next_location) {
+            candidates.push((
+                next_location,
+                GuardState {
+                    location: guard_location,
+                    direction: guard_direction,
+                },
+            ));
+        }
+        guard_location = next_location;
+    }
+
+    candidates
+}
+
+/// Counts the obstacle locations that make the guard loop, checking the candidates in parallel.
+fn count_loop_obstacles(map: &Map, guard_location: Location, guard_direction: Direction) -> usize {
+    let jump_table = JumpTable::from_map(map);
+    let candidates = obstacle_candidates(map, guard_location, guard_direction);
+
+    let num_threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = candidates.len().div_ceil(num_threads).max(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = candidates
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let jump_table = &jump_table;
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .filter(|(obstacle, start_state)| {
+                            jump_table.has_loop(*start_state, *obstacle)
+                        })
+                        .count()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .sum()
+    })
+}
+
 fn task1() -> Result<(), Error> {
     println!("Computing solution for task 1 of Day 6...");
 
@@ -193,20 +427,8 @@ fn task2() -> Result<(), Error> {
     let input_data = fs::read_to_string("input_data/day06_input.txt")?;
 
     // guard location is row, col
-    let (mut map, guard_location, guard_direction) = Map::from_string(input_data);
-    let (_, original_visited_locations) = walk_guard(&map, guard_location, guard_direction);
-
-    // just add an obstacle and see if there is a loop
-    let mut num_loop_locations: u32 = 0;
-    for new_obstacle_location in original_visited_locations {
-        map.barriers.insert(new_obstacle_location);
-        let (has_loop, _) = walk_guard(&map, guard_location, guard_direction);
-        map.barriers.remove(&new_obstacle_location);
-
-        if has_loop {
-            num_loop_locations += 1;
-        }
-    }
+    let (map, guard_location, guard_direction) = Map::from_string(input_data);
+    let num_loop_locations = count_loop_obstacles(&map, guard_location, guard_direction);
 
     let mut solution_file = fs::OpenOptions::new()
         .append(true)
@@ -222,3 +444,32 @@ fn task2() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn original_path_that_loops() {
+        let (map, guard_location, guard_direction) =
+            Map::from_string(".#..\n...#\n#^..\n..#.".to_string());
+        let (has_loop, _) = walk_guard(&map, guard_location, guard_direction);
+        assert!(has_loop);
+
+        // the count has to finish, and agree with walking the guard for every candidate
+        let num_loop_obstacles = count_loop_obstacles(&map, guard_location, guard_direction);
+        let mut reference = 0;
+        for (obstacle, _) in obstacle_candidates(&map, guard_location, guard_direction) {
+            let mut blocked_map = Map {
+                barriers: map.barriers.clone(),
+                height: map.height,
+                width: map.width,
+            };
+            blocked_map.barriers.insert(obstacle);
+            if walk_guard(&blocked_map, guard_location, guard_direction).0 {
+                reference += 1;
+            }
+        }
+        assert_eq!(num_loop_obstacles, reference);
+    }
+}
@@ -1,73 +1,388 @@
 use std::fs;
+use std::io::Error;
 use std::io::Write;
-use std::io::{Error, ErrorKind};
+use std::time::Instant;
 
+/// `--bench` times the forward and backward searches against each other.
 pub fn run() -> Result<(), Error> {
     let _ = task1();
     let _ = task2();
+    if std::env::args().any(|arg| arg == "--bench") {
+        let _ = benchmark_search_directions();
+    }
 
     println!("Completed solutions for Day 7!");
 
     Ok(())
 }
 
-fn check_equation(
-    output_value: u64,
-    numbers: Vec<u64>,
-    operators: Vec<&str>,
-) -> Result<bool, Error> {
-    let mut valid = false;
-    let mut stack: Vec<(usize, u64)> = vec![(1, *numbers.first().expect("numbers is empty!"))];
-
-    while let Some((current_idx, current_result)) = stack.pop() {
-        if (current_idx == numbers.len()) & (current_result == output_value) {
-            valid = true;
-            break;
-        }
-        if let Some(&current_number) = numbers.get(current_idx) {
-            for operator in operators.iter() {
-                let result: u64;
-                if operator == &"add" {
-                    result = current_result + current_number;
-                } else if operator == &"mul" {
-                    result = current_result * current_number;
-                } else if operator == &"cat" {
-                    result =
-                        current_result * 10u64.pow(current_number.ilog10() + 1) + current_number
-                } else {
-                    eprintln!("Unknown operator {}!", operator);
-                    return Err(Error::new(ErrorKind::InvalidInput, "Unknown Operator"));
-                }
-                if result <= output_value {
-                    stack.push((current_idx + 1, result));
-                }
-            }
-        }
+/// The left operands that give a certain result together with a certain right operand.
+enum LeftOperand {
+    Impossible,
+    Exactly(u128),
+    // e.g. multiplying by 0
+    Any,
+}
+impl From<Option<u128>> for LeftOperand {
+    fn from(left: Option<u128>) -> LeftOperand {
+        left.map_or(LeftOperand::Impossible, LeftOperand::Exactly)
     }
+}
 
-    Ok(valid)
+/// A binary operator that can be placed between two numbers of an equation. `undo` is the
+/// inverse: given a result and the right operand it recovers the left operand, if there is one.
+#[derive(Clone, Copy)]
+struct Operator {
+    symbol: &'static str,
+    apply: fn(u128, u128) -> Option<u128>,
+    undo: Option<fn(u128, u128) -> LeftOperand>,
+    // whether the result is never smaller than the left operand for the given right operand; once
+    // no later number can make the result smaller, partial results that already exceed the test
+    // value can be discarded
+    non_decreasing: fn(u128) -> bool,
 }
 
-fn task1() -> Result<(), Error> {
-    println!("Computing solution for task 1 of Day 7...");
+fn num_digits(number: u128) -> u32 {
+    number.checked_ilog10().unwrap_or(0) + 1
+}
 
-    let input_data = fs::read_to_string("input_data/day07_input.txt")?;
+const ADD: Operator = Operator {
+    symbol: "+",
+    apply: |left, right| left.checked_add(right),
+    undo: Some(|result, right| result.checked_sub(right).into()),
+    non_decreasing: |_| true,
+};
 
-    let mut sum_of_valid_test_values: u64 = 0;
-    for line in input_data.lines() {
+const MUL: Operator = Operator {
+    symbol: "*",
+    apply: |left, right| left.checked_mul(right),
+    undo: Some(|result, right| {
+        if right == 0 {
+            if result == 0 {
+                LeftOperand::Any
+            } else {
+                LeftOperand::Impossible
+            }
+        } else if result % right == 0 {
+            LeftOperand::Exactly(result / right)
+        } else {
+            LeftOperand::Impossible
+        }
+    }),
+    non_decreasing: |right| right != 0,
+};
+
+const CAT: Operator = Operator {
+    symbol: "||",
+    apply: |left, right| {
+        left.checked_mul(10u128.checked_pow(num_digits(right))?)?
+            .checked_add(right)
+    },
+    undo: Some(|result, right| {
+        let Some(shift) = 10u128.checked_pow(num_digits(right)) else {
+            return LeftOperand::Impossible;
+        };
+        if result % shift == right {
+            LeftOperand::Exactly(result / shift)
+        } else {
+            LeftOperand::Impossible
+        }
+    }),
+    non_decreasing: |_| true,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum SearchDirection {
+    // apply the operators left to right, starting from the first number
+    Forward,
+    // undo the operators right to left, starting from the test value
+    Backward,
+}
+
+struct Equation {
+    test_value: u128,
+    numbers: Vec<u128>,
+}
+impl Equation {
+    pub fn from_str(line: &str) -> Equation {
         let (test_value_str, numbers_str) = line.split_once(':').expect("Line has no ':'!");
 
         let test_value = test_value_str
-            .parse::<u64>()
+            .parse::<u128>()
             .expect("could not parse test value.");
 
-        let numbers: Vec<u64> = numbers_str
+        let numbers: Vec<u128> = numbers_str
             .split_whitespace()
-            .filter_map(|s| s.parse::<u64>().ok())
+            .filter_map(|s| s.parse::<u128>().ok())
             .collect();
 
-        if check_equation(test_value, numbers, vec!["add", "mul"])? {
-            sum_of_valid_test_values += test_value;
+        Equation {
+            test_value,
+            numbers,
+        }
+    }
+
+    /// Returns the first assignment of operators (one per gap between numbers) that makes the
+    /// equation true.
+    pub fn first_solution(&self, operators: &[Operator]) -> Option<Vec<Operator>> {
+        self.solutions(operators, preferred_direction(operators), false)
+            .pop()
+    }
+
+    /// Returns every assignment of operators that makes the equation true.
+    pub fn all_solutions(&self, operators: &[Operator]) -> Vec<Vec<Operator>> {
+        self.solutions(operators, preferred_direction(operators), true)
+    }
+
+    /// The backward search requires every operator to have an `undo`.
+    pub fn solutions(
+        &self,
+        operators: &[Operator],
+        direction: SearchDirection,
+        find_all: bool,
+    ) -> Vec<Vec<Operator>> {
+        let mut solutions = Vec::new();
+        match direction {
+            SearchDirection::Forward => self.search_forward(operators, find_all, &mut solutions),
+            SearchDirection::Backward => self.search_backward(operators, find_all, &mut solutions),
+        }
+        solutions
+    }
+
+    /// Writes the equation with the given operators filled in, e.g. `190 = 10 * 19`.
+    pub fn format_solution(&self, operators: &[Operator]) -> String {
+        let mut formatted = format!("{} = {}", self.test_value, self.numbers[0]);
+        for (operator, number) in operators.iter().zip(self.numbers.iter().skip(1)) {
+            formatted.push_str(&format!(" {} {}", operator.symbol, number));
+        }
+        formatted
+    }
+
+    fn search_forward(
+        &self,
+        operators: &[Operator],
+        find_all: bool,
+        solutions: &mut Vec<Vec<Operator>>,
+    ) {
+        let Some(&first_number) = self.numbers.first() else {
+            return;
+        };
+        let mut chosen_operators = Vec::with_capacity(self.numbers.len() - 1);
+        self.recurse_forward(
+            operators,
+            first_number,
+            &mut chosen_operators,
+            find_all,
+            solutions,
+        );
+    }
+
+    fn search_backward(
+        &self,
+        operators: &[Operator],
+        find_all: bool,
+        solutions: &mut Vec<Vec<Operator>>,
+    ) {
+        if self.numbers.is_empty() {
+            return;
+        }
+        let mut chosen_operators = Vec::with_capacity(self.numbers.len() - 1);
+        self.recurse_backward(
+            operators,
+            self.test_value,
+            &mut chosen_operators,
+            find_all,
+            solutions,
+        );
+    }
+
+    /// Every way to place operators between the first `num_numbers` numbers without overflowing.
+    fn all_operator_choices(
+        &self,
+        operators: &[Operator],
+        num_numbers: usize,
+    ) -> Vec<Vec<Operator>> {
+        let mut choices = vec![(self.numbers[0], Vec::new())];
+        for &number in self.numbers[1..num_numbers].iter() {
+            choices = choices
+                .into_iter()
+                .flat_map(|(result, chosen_operators)| {
+                    operators.iter().filter_map(move |operator| {
+                        let mut chosen_operators = chosen_operators.clone();
+                        chosen_operators.push(*operator);
+                        Some(((operator.apply)(result, number)?, chosen_operators))
+                    })
+                })
+                .collect();
+        }
+        choices
+            .into_iter()
+            .map(|(_, chosen_operators)| chosen_operators)
+            .collect()
+    }
+
+    // returns true once the search can stop
+    fn recurse_backward(
+        &self,
+        operators: &[Operator],
+        remaining_value: u128,
+        chosen_operators: &mut Vec<Operator>,
+        find_all: bool,
+        solutions: &mut Vec<Vec<Operator>>,
+    ) -> bool {
+        // operators are chosen from the back, so the number they act on is further to the left
+        // with every operator chosen
+        let current_idx = self.numbers.len() - 1 - chosen_operators.len();
+        if current_idx == 0 {
+            if remaining_value == self.numbers[0] {
+                solutions.push(chosen_operators.iter().rev().copied().collect());
+                return !find_all;
+            }
+            return false;
+        }
+        let current_number = self.numbers[current_idx];
+
+        for operator in operators {
+            let undo = operator
+                .undo
+                .expect("backward search needs undoable operators!");
+            let previous_value = match undo(remaining_value, current_number) {
+                LeftOperand::Impossible => continue,
+                LeftOperand::Exactly(previous_value) => previous_value,
+                LeftOperand::Any => {
+                    // whatever the numbers to the left evaluate to works
+                    for mut solution in self.all_operator_choices(operators, current_idx) {
+                        solution.push(*operator);
+                        solution.extend(chosen_operators.iter().rev());
+                        solutions.push(solution);
+                        if !find_all {
+                            return true;
+                        }
+                    }
+                    continue;
+                }
+            };
+
+            chosen_operators.push(*operator);
+            let done = self.recurse_backward(
+                operators,
+                previous_value,
+                chosen_operators,
+                find_all,
+                solutions,
+            );
+            chosen_operators.pop();
+            if done {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    // returns true once the search can stop
+    fn recurse_forward(
+        &self,
+        operators: &[Operator],
+        current_result: u128,
+        chosen_operators: &mut Vec<Operator>,
+        find_all: bool,
+        solutions: &mut Vec<Vec<Operator>>,
+    ) -> bool {
+        let current_idx = chosen_operators.len() + 1;
+        let Some(&current_number) = self.numbers.get(current_idx) else {
+            if current_result == self.test_value {
+                solutions.push(chosen_operators.clone());
+                return !find_all;
+            }
+            return false;
+        };
+        let is_last_number = current_idx == self.numbers.len() - 1;
+        let can_shrink_later = self.numbers[current_idx + 1..].iter().any(|&number| {
+            operators
+                .iter()
+                .any(|operator| !(operator.non_decreasing)(number))
+        });
+
+        for operator in operators {
+            let result = if let (true, Some(undo)) = (is_last_number, operator.undo) {
+                // for the last number it is cheaper to undo the operator from the test value
+                match undo(self.test_value, current_number) {
+                    LeftOperand::Exactly(left) if left == current_result => {}
+                    LeftOperand::Any => {}
+                    _ => continue,
+                }
+                self.test_value
+            } else {
+                match (operator.apply)(current_result, current_number) {
+                    Some(result) => result,
+                    None => continue,
+                }
+            };
+            if result > self.test_value && !can_shrink_later {
+                continue;
+            }
+
+            chosen_operators.push(*operator);
+            let done =
+                self.recurse_forward(operators, result, chosen_operators, find_all, solutions);
+            chosen_operators.pop();
+            if done {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+fn preferred_direction(operators: &[Operator]) -> SearchDirection {
+    if operators.iter().all(|operator| operator.undo.is_some()) {
+        SearchDirection::Backward
+    } else {
+        SearchDirection::Forward
+    }
+}
+
+/// Times the forward and backward searches against each other on the puzzle input.
+fn benchmark_search_directions() -> Result<(), Error> {
+    println!("Benchmarking equation search directions for Day 7...");
+
+    let input_data = fs::read_to_string("input_data/day07_input.txt")?;
+    let equations: Vec<Equation> = input_data.lines().map(Equation::from_str).collect();
+
+    for operators in [vec![ADD, MUL], vec![ADD, MUL, CAT]] {
+        let operator_symbols: Vec<&str> =
+            operators.iter().map(|operator| operator.symbol).collect();
+        for direction in [SearchDirection::Forward, SearchDirection::Backward] {
+            let start_time = Instant::now();
+            let num_valid_equations = equations
+                .iter()
+                .filter(|equation| !equation.solutions(&operators, direction, false).is_empty())
+                .count();
+            println!(
+                "  {:?} search with operators [{}]: {} valid equations in {:.2?}",
+                direction,
+                operator_symbols.join(", "),
+                num_valid_equations,
+                start_time.elapsed()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn task1() -> Result<(), Error> {
+    println!("Computing solution for task 1 of Day 7...");
+
+    let input_data = fs::read_to_string("input_data/day07_input.txt")?;
+
+    let mut sum_of_valid_test_values: u128 = 0;
+    for line in input_data.lines() {
+        let equation = Equation::from_str(line);
+        if equation.first_solution(&[ADD, MUL]).is_some() {
+            sum_of_valid_test_values += equation.test_value;
         }
     }
 
@@ -87,21 +402,23 @@ fn task2() -> Result<(), Error> {
 
     let input_data = fs::read_to_string("input_data/day07_input.txt")?;
 
-    let mut sum_of_valid_test_values: u64 = 0;
+    let mut sum_of_valid_test_values: u128 = 0;
+    let mut num_ambiguous_equations = 0;
+    let mut ambiguous_example: Option<(String, String)> = None;
     for line in input_data.lines() {
-        let (test_value_str, numbers_str) = line.split_once(':').expect("Line has no ':'!");
-
-        let test_value = test_value_str
-            .parse::<u64>()
-            .expect("could not parse test value.");
-
-        let numbers: Vec<u64> = numbers_str
-            .split_whitespace()
-            .filter_map(|s| s.parse::<u64>().ok())
-            .collect();
-
-        if check_equation(test_value, numbers, vec!["add", "mul", "cat"])? {
-            sum_of_valid_test_values += test_value;
+        let equation = Equation::from_str(line);
+        let solutions = equation.all_solutions(&[ADD, MUL, CAT]);
+        if !solutions.is_empty() {
+            sum_of_valid_test_values += equation.test_value;
+        }
+        if solutions.len() > 1 {
+            num_ambiguous_equations += 1;
+            ambiguous_example.get_or_insert_with(|| {
+                (
+                    equation.format_solution(&solutions[0]),
+                    equation.format_solution(&solutions[1]),
+                )
+            });
         }
     }
 
@@ -112,6 +429,51 @@ fn task2() -> Result<(), Error> {
     writeln!(solution_file)?;
     writeln!(solution_file, "Solution for Task 2 of Day 07:")?;
     writeln!(solution_file, "The sum of the test values of equations that could be true with addition, multiplication, and concatenation is {}.", sum_of_valid_test_values)?;
+    writeln!(
+        solution_file,
+        "{} of these equations can be made true in more than one way.",
+        num_ambiguous_equations
+    )?;
+    if let Some((first_solution, second_solution)) = ambiguous_example {
+        writeln!(
+            solution_file,
+            "For example, both {} and {} hold.",
+            first_solution, second_solution
+        )?;
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatted_solutions(equation: &Equation, direction: SearchDirection) -> Vec<String> {
+        let mut formatted: Vec<String> = equation
+            .solutions(&[ADD, MUL, CAT], direction, true)
+            .iter()
+            .map(|solution| equation.format_solution(solution))
+            .collect();
+        formatted.sort();
+        formatted
+    }
+
+    #[test]
+    fn multiplying_by_zero() {
+        let equation = Equation::from_str("0: 5 0");
+        for direction in [SearchDirection::Forward, SearchDirection::Backward] {
+            assert_eq!(formatted_solutions(&equation, direction), vec!["0 = 5 * 0"]);
+        }
+
+        let equation = Equation::from_str("0: 2 3 0 4");
+        let expected = vec![
+            "0 = 2 * 3 * 0 * 4",
+            "0 = 2 + 3 * 0 * 4",
+            "0 = 2 || 3 * 0 * 4",
+        ];
+        for direction in [SearchDirection::Forward, SearchDirection::Backward] {
+            assert_eq!(formatted_solutions(&equation, direction), expected);
+        }
+    }
+}
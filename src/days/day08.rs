@@ -23,7 +23,6 @@ struct Location {
 }
 
 type AntennaLocations = HashMap<char, Vec<Location>>;
-type AntennaPair = (Location, Location);
 
 fn parse_map(map: Vec<Vec<char>>) -> (AntennaLocations, isize, isize) {
     let mut antenna_locations: AntennaLocations = HashMap::new();
@@ -59,19 +58,151 @@ fn parse_map(map: Vec<Vec<char>>) -> (AntennaLocations, isize, isize) {
     )
 }
 
-fn make_pairs(antenna_locations: AntennaLocations) -> Vec<AntennaPair> {
-    let mut antenna_pairs: Vec<AntennaPair> = Vec::new();
+/// Which points along the line through two antennas of the same frequency become antinodes.
+#[derive(Clone, Copy)]
+enum ResonanceRule {
+    // one antinode on either side, as far from the nearer antenna as the antennas are apart
+    TwiceDistance,
+    // every grid point on the line through the antennas, including the antennas themselves
+    ResonantLine,
+    // the points reached by moving min..=max multiples of the antenna distance beyond each antenna
+    Harmonics { min: isize, max: isize },
+}
+
+struct Antinodes {
+    all: HashSet<Location>,
+    by_frequency: HashMap<char, HashSet<Location>>,
+}
 
-    for frequency in antenna_locations.keys() {
-        let locations = antenna_locations.get(frequency).unwrap();
+fn is_on_map(location: Location, height: isize, width: isize) -> bool {
+    (location.row >= 0) && (location.row < height) && (location.col >= 0) && (location.col < width)
+}
+
+/// Walks from `start` in steps of (row_step, col_step) and collects the locations for every
+/// factor from `min_factor` until `max_factor` or the edge of the map.
+fn collect_along_line(
+    start: Location,
+    (row_step, col_step): (isize, isize),
+    min_factor: isize,
+    max_factor: Option<isize>,
+    height: isize,
+    width: isize,
+    antinodes: &mut HashSet<Location>,
+) {
+    let mut factor = min_factor;
+    while max_factor.is_none_or(|max_factor| factor <= max_factor) {
+        let location = Location {
+            row: start.row + factor * row_step,
+            col: start.col + factor * col_step,
+        };
+        if !is_on_map(location, height, width) {
+            break;
+        }
+        antinodes.insert(location);
+        factor += 1;
+    }
+}
+
+fn pair_antinodes(
+    location_a: Location,
+    location_b: Location,
+    rule: ResonanceRule,
+    height: isize,
+    width: isize,
+    antinodes: &mut HashSet<Location>,
+) {
+    let row_diff = location_b.row - location_a.row;
+    let col_diff = location_b.col - location_a.col;
+
+    let (step, min_factor, max_factor) = match rule {
+        ResonanceRule::TwiceDistance => ((row_diff, col_diff), 1, Some(1)),
+        ResonanceRule::Harmonics { min, max } => ((row_diff, col_diff), min, Some(max)),
+        ResonanceRule::ResonantLine => {
+            // antinodes could be between antennas, so walk both ways from a with the reduced step
+            let step = reduce_location_diff(row_diff, col_diff);
+            collect_along_line(location_a, step, 0, None, height, width, antinodes);
+            collect_along_line(
+                location_a,
+                (-step.0, -step.1),
+                1,
+                None,
+                height,
+                width,
+                antinodes,
+            );
+            return;
+        }
+    };
+    let reversed_step = (-step.0, -step.1);
+
+    // away from b, starting at a
+    collect_along_line(
+        location_a,
+        reversed_step,
+        min_factor,
+        max_factor,
+        height,
+        width,
+        antinodes,
+    );
+    // away from a, starting at b
+    collect_along_line(
+        location_b, step, min_factor, max_factor, height, width, antinodes,
+    );
+}
+
+/// Computes the antinodes of all antenna pairs of the same frequency under the given rule.
+fn compute_antinodes(
+    antenna_locations: &AntennaLocations,
+    height: isize,
+    width: isize,
+    rule: ResonanceRule,
+) -> Antinodes {
+    let mut all = HashSet::new();
+    let mut by_frequency = HashMap::new();
+
+    for (frequency, locations) in antenna_locations {
+        let mut frequency_antinodes = HashSet::new();
         for i in 0..locations.len() {
             for j in (i + 1)..locations.len() {
-                antenna_pairs.push((locations[i], locations[j]));
+                pair_antinodes(
+                    locations[i],
+                    locations[j],
+                    rule,
+                    height,
+                    width,
+                    &mut frequency_antinodes,
+                );
             }
         }
+        all.extend(frequency_antinodes.iter().copied());
+        by_frequency.insert(*frequency, frequency_antinodes);
     }
 
-    antenna_pairs
+    Antinodes { all, by_frequency }
+}
+
+/// Renders the map with antinodes marked as '#'; antennas are drawn on top of antinodes.
+fn render_antinodes(
+    antenna_locations: &AntennaLocations,
+    antinodes: &HashSet<Location>,
+    height: isize,
+    width: isize,
+) -> String {
+    let mut grid = vec![vec!['.'; width as usize]; height as usize];
+    for location in antinodes {
+        grid[location.row as usize][location.col as usize] = '#';
+    }
+    for (frequency, locations) in antenna_locations {
+        for location in locations {
+            grid[location.row as usize][location.col as usize] = *frequency;
+        }
+    }
+
+    grid.iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
 }
 
 pub fn run() -> Result<(), Error> {
@@ -92,41 +223,19 @@ fn task1() -> Result<(), Error> {
         .collect();
 
     let (antenna_locations, height, width) = parse_map(input_data);
-    let antenna_pairs = make_pairs(antenna_locations);
-
-    let mut unique_antinodes: HashSet<Location> = HashSet::new();
-
-    for (location_a, location_b) in antenna_pairs {
-        let row_diff = location_b.row - location_a.row;
-        let col_diff = location_b.col - location_a.col;
-
-        let new_row1 = location_a.row - row_diff;
-        let new_col1 = location_a.col - col_diff;
-
-        if (new_row1 >= 0) && (new_row1 < height) && (new_col1 >= 0) && (new_col1 < width) {
-            unique_antinodes.insert(Location {
-                row: new_row1,
-                col: new_col1,
-            });
-        }
-
-        let new_row2 = location_b.row + row_diff;
-        let new_col2 = location_b.col + col_diff;
-
-        if (new_row2 >= 0) && (new_row2 < height) && (new_col2 >= 0) && (new_col2 < width) {
-            unique_antinodes.insert(Location {
-                row: new_row2,
-                col: new_col2,
-            });
-        }
-    }
+    let antinodes = compute_antinodes(
+        &antenna_locations,
+        height,
+        width,
+        ResonanceRule::TwiceDistance,
+    );
 
     let mut solution_file = fs::File::create("solutions/day08_solution.txt")?;
     writeln!(solution_file, "Solution for Task 1 of Day 08:")?;
     writeln!(
         solution_file,
         "The map contains {} unique antinodes.",
-        unique_antinodes.len()
+        antinodes.all.len()
     )?;
 
     Ok(())
@@ -141,51 +250,39 @@ fn task2() -> Result<(), Error> {
         .collect();
 
     let (antenna_locations, height, width) = parse_map(input_data);
-    let antenna_pairs = make_pairs(antenna_locations);
-
-    let mut resonant_antinodes: HashSet<Location> = HashSet::new();
-
-    for (location_a, location_b) in antenna_pairs {
-        let raw_row_diff = location_b.row - location_a.row;
-        let raw_col_diff = location_b.col - location_a.col;
-
-        let (row_diff, col_diff) = reduce_location_diff(raw_row_diff, raw_col_diff);
-
-        // Direction 1
-        let mut factor = 0;
-        loop {
-            let new_row = location_a.row - factor * row_diff;
-            let new_col = location_a.col - factor * col_diff;
-
-            if (new_row >= 0) && (new_row < height) && (new_col >= 0) && (new_col < width) {
-                resonant_antinodes.insert(Location {
-                    row: new_row,
-                    col: new_col,
-                });
-            } else {
-                break;
-            }
-
-            factor += 1;
-        }
-
-        // Direction 2
-        let mut factor = 0;
-        loop {
-            let new_row = location_a.row + factor * row_diff; // antinodes could be between antennas
-            let new_col = location_a.col + factor * col_diff;
-
-            if (new_row >= 0) && (new_row < height) && (new_col >= 0) && (new_col < width) {
-                resonant_antinodes.insert(Location {
-                    row: new_row,
-                    col: new_col,
-                });
-            } else {
-                break;
-            }
-
-            factor += 1;
-        }
+    let antinodes = compute_antinodes(
+        &antenna_locations,
+        height,
+        width,
+        ResonanceRule::ResonantLine,
+    );
+    let harmonic_antinodes = compute_antinodes(
+        &antenna_locations,
+        height,
+        width,
+        ResonanceRule::Harmonics { min: 0, max: 2 },
+    );
+    let mut map_file = fs::File::create("solutions/day08_task2_antinode_map.txt")?;
+    writeln!(
+        map_file,
+        "{}",
+        render_antinodes(&antenna_locations, &antinodes.all, height, width)
+    )?;
+    writeln!(map_file)?;
+    writeln!(
+        map_file,
+        "Limited to the antennas and their first two harmonics there would be {} antinodes.",
+        harmonic_antinodes.all.len()
+    )?;
+    let mut frequencies: Vec<&char> = antinodes.by_frequency.keys().collect();
+    frequencies.sort();
+    for frequency in frequencies {
+        writeln!(
+            map_file,
+            "Frequency {}: {} resonant antinodes",
+            frequency,
+            antinodes.by_frequency[frequency].len()
+        )?;
     }
 
     let mut solution_file = fs::OpenOptions::new()
@@ -197,8 +294,33 @@ fn task2() -> Result<(), Error> {
     writeln!(
         solution_file,
         "The map contains {} unique resonant antinodes.",
-        resonant_antinodes.len()
+        antinodes.all.len()
     )?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resonant_line_includes_points_between_antennas() {
+        let mut antinodes = HashSet::new();
+        pair_antinodes(
+            Location { row: 0, col: 0 },
+            Location { row: 2, col: 4 },
+            ResonanceRule::ResonantLine,
+            5,
+            5,
+            &mut antinodes,
+        );
+
+        let mut antinodes: Vec<(isize, isize)> = antinodes
+            .iter()
+            .map(|location| (location.row, location.col))
+            .collect();
+        antinodes.sort();
+        assert_eq!(antinodes, vec![(0, 0), (1, 2), (2, 4)]);
+    }
+}
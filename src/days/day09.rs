@@ -1,3 +1,5 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::fs;
 use std::io::Error;
 use std::io::Write;
@@ -8,86 +10,246 @@ struct ContiguousBlock {
     end: u64,
 }
 
-fn parse_diskmap(diskmap: Vec<u32>) -> (Vec<ContiguousBlock>, Vec<ContiguousBlock>) {
-    let mut empty_blocks = Vec::new();
-    let mut file_locations = Vec::new();
-
-    let mut current_location: u64 = 0;
-    for (block_num, length) in diskmap.iter().enumerate() {
-        if block_num % 2 == 0 {
-            // file
-            file_locations.push(ContiguousBlock {
-                start: current_location,
-                end: current_location + *length as u64,
-            });
-            current_location += *length as u64;
-        } else {
-            // empty space
-            empty_blocks.push(ContiguousBlock {
-                start: current_location,
-                end: current_location + *length as u64,
-            });
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CompactionStrategy {
+    // move single blocks from the end of the disk into the leftmost free block
+    BlockLevel,
+    // move each file once, in order of decreasing file ID, into the leftmost free span that fits it
+    WholeFile,
+}
+
+/// A disk as a list of files, each made up of one or more contiguous blocks, indexed by file ID.
+/// Everything not covered by a file is free space.
+struct Disk {
+    files: Vec<Vec<ContiguousBlock>>,
+    size: u64,
+}
+impl Disk {
+    pub fn from_diskmap(diskmap: &[u32]) -> Disk {
+        let mut files = Vec::new();
+
+        let mut current_location: u64 = 0;
+        for (block_num, length) in diskmap.iter().enumerate() {
+            if block_num % 2 == 0 {
+                // file
+                files.push(vec![ContiguousBlock {
+                    start: current_location,
+                    end: current_location + *length as u64,
+                }]);
+            }
+            // empty space only advances the location
             current_location += *length as u64;
         }
+
+        Disk {
+            files,
+            size: current_location,
+        }
     }
 
-    (empty_blocks, file_locations)
-}
+    /// The free spans between (and after) the files, sorted by location.
+    pub fn free_spaces(&self) -> Vec<ContiguousBlock> {
+        let mut used_blocks: Vec<ContiguousBlock> = self.files.iter().flatten().copied().collect();
+        used_blocks.sort_by_key(|block| block.start);
 
-pub fn run() -> Result<(), Error> {
-    let _ = task1();
-    let _ = task2();
+        let mut free_spaces = Vec::new();
+        let mut current_location = 0;
+        for block in used_blocks.iter().chain(std::iter::once(&ContiguousBlock {
+            start: self.size,
+            end: self.size,
+        })) {
+            if block.start > current_location {
+                free_spaces.push(ContiguousBlock {
+                    start: current_location,
+                    end: block.start,
+                });
+            }
+            current_location = current_location.max(block.end);
+        }
 
-    println!("Completed solutions for Day 9!");
+        free_spaces
+    }
 
-    Ok(())
-}
+    pub fn compact(&mut self, strategy: CompactionStrategy) {
+        match strategy {
+            CompactionStrategy::BlockLevel => self.compact_blocks(),
+            CompactionStrategy::WholeFile => self.compact_whole_files(),
+        }
+    }
 
-fn task1() -> Result<(), Error> {
-    println!("Computing solution for task 1 of Day 9...");
+    fn compact_blocks(&mut self) {
+        let mut free_spaces = self.free_spaces();
+        let mut empty_space_idx = 0;
+        let mut file_idx = self.files.len();
 
-    let input_data = fs::read_to_string("input_data/day09_input.txt")?;
-    let diskmap: Vec<u32> = input_data
-        .chars()
-        .map(|c| {
-            c.to_digit(10)
-                .expect("Input string contains non-digit characters")
-        })
-        .collect();
+        // the first block of every file is its original one, since moved blocks are added at the end
+        while file_idx > 0 && empty_space_idx < free_spaces.len() {
+            let free_space = &mut free_spaces[empty_space_idx];
+            let file_block = self.files[file_idx - 1][0];
+            if file_block.start == file_block.end {
+                file_idx -= 1;
+                continue;
+            }
+            if free_space.start == free_space.end {
+                empty_space_idx += 1;
+                continue;
+            }
+            if free_space.start >= file_block.end {
+                break;
+            }
 
-    let (mut empty_blocks, mut file_locations) = parse_diskmap(diskmap);
-    let mut checksum = 0;
+            let num_moved =
+                (free_space.end - free_space.start).min(file_block.end - file_block.start);
+            self.files[file_idx - 1].push(ContiguousBlock {
+                start: free_space.start,
+                end: free_space.start + num_moved,
+            });
+            self.files[file_idx - 1][0].end -= num_moved;
+            free_space.start += num_moved;
+        }
 
-    let mut file_idx = file_locations.len() - 1;
-    let mut empty_space_idx: usize = 0;
+        for file in self.files.iter_mut() {
+            file.retain(|block| block.start < block.end);
+        }
+    }
 
-    loop {
-        if empty_blocks[empty_space_idx].start == empty_blocks[empty_space_idx].end {
-            empty_space_idx += 1;
-            continue;
+    fn compact_whole_files(&mut self) {
+        // free_space_starts[length] holds the start locations of all free spans of that length
+        let free_spaces = self.free_spaces();
+        let max_length = free_spaces
+            .iter()
+            .map(|space| (space.end - space.start) as usize)
+            .max()
+            .unwrap_or(0);
+        let mut free_space_starts: Vec<BinaryHeap<Reverse<u64>>> =
+            vec![BinaryHeap::new(); max_length + 1];
+        for space in free_spaces {
+            free_space_starts[(space.end - space.start) as usize].push(Reverse(space.start));
         }
-        if file_locations[file_idx].start == file_locations[file_idx].end {
-            file_idx -= 1;
-            continue;
+
+        for file in self.files.iter_mut().rev() {
+            let Some(file_block) = file.first().copied() else {
+                continue;
+            };
+            let file_length = (file_block.end - file_block.start) as usize;
+            if file_length == 0 || file_length > max_length {
+                continue;
+            }
+
+            // the leftmost free span that fits the file, over all lengths that are large enough
+            let Some((space_length, space_start)) = (file_length..=max_length)
+                .filter_map(|length| {
+                    free_space_starts[length]
+                        .peek()
+                        .map(|Reverse(start)| (length, *start))
+                })
+                .min_by_key(|(_, start)| *start)
+            else {
+                continue;
+            };
+            if space_start >= file_block.start {
+                continue;
+            }
+
+            free_space_starts[space_length].pop();
+            *file = vec![ContiguousBlock {
+                start: space_start,
+                end: space_start + file_length as u64,
+            }];
+            // the space left behind by the file is never used again, since all remaining files
+            // are further to the left
+            let remaining_length = space_length - file_length;
+            if remaining_length > 0 {
+                free_space_starts[remaining_length].push(Reverse(space_start + file_length as u64));
+            }
         }
-        if (empty_space_idx == empty_blocks.len())
-            || (empty_blocks[empty_space_idx].start >= file_locations[file_idx].end)
-        {
-            // sum up all the remaining file blocks
-            for (file_id, &block) in file_locations[..file_idx + 1].iter().enumerate() {
+    }
+
+    pub fn checksum(&self) -> u64 {
+        let mut checksum = 0;
+        for (file_id, file) in self.files.iter().enumerate() {
+            for block in file {
                 checksum += (((block.start + block.end - 1) * (block.end - block.start)) / 2)
                     * file_id as u64;
             }
-            break;
         }
-        if file_idx == 0 {
-            break;
+        checksum
+    }
+
+    /// Renders the disk like the puzzle, e.g. `00...111...2...`; file IDs above 9 are shown
+    /// with their last digit only.
+    pub fn render(&self) -> String {
+        let mut layout = vec!['.'; self.size as usize];
+        for (file_id, file) in self.files.iter().enumerate() {
+            let digit = char::from_digit((file_id % 10) as u32, 10).unwrap();
+            for block in file {
+                for location in block.start..block.end {
+                    layout[location as usize] = digit;
+                }
+            }
         }
-        checksum += empty_blocks[empty_space_idx].start * file_idx as u64;
-        empty_blocks[empty_space_idx].start += 1;
-        file_locations[file_idx].end -= 1;
+        layout.into_iter().collect()
+    }
+}
+
+fn parse_diskmap(input_data: &str) -> Vec<u32> {
+    input_data
+        .trim()
+        .chars()
+        .map(|c| {
+            c.to_digit(10)
+                .expect("Input string contains non-digit characters")
+        })
+        .collect()
+}
+
+/// Writes the compaction of the example disk map from the puzzle for both strategies.
+fn write_example_layouts() -> Result<(), Error> {
+    let diskmap = parse_diskmap("2333133121414131402");
+
+    let mut layout_file = fs::File::create("solutions/day09_example_layouts.txt")?;
+    for (strategy_name, strategy) in [
+        ("block-level", CompactionStrategy::BlockLevel),
+        ("whole-file", CompactionStrategy::WholeFile),
+    ] {
+        let mut disk = Disk::from_diskmap(&diskmap);
+        writeln!(
+            layout_file,
+            "Before {} compaction: {}",
+            strategy_name,
+            disk.render()
+        )?;
+        disk.compact(strategy);
+        writeln!(
+            layout_file,
+            "After {} compaction:  {}",
+            strategy_name,
+            disk.render()
+        )?;
+        writeln!(layout_file, "Checksum: {}", disk.checksum())?;
     }
 
+    Ok(())
+}
+
+pub fn run() -> Result<(), Error> {
+    let _ = task1();
+    let _ = task2();
+    let _ = write_example_layouts();
+
+    println!("Completed solutions for Day 9!");
+
+    Ok(())
+}
+
+fn task1() -> Result<(), Error> {
+    println!("Computing solution for task 1 of Day 9...");
+
+    let input_data = fs::read_to_string("input_data/day09_input.txt")?;
+    let mut disk = Disk::from_diskmap(&parse_diskmap(&input_data));
+    disk.compact(CompactionStrategy::BlockLevel);
+    let checksum = disk.checksum();
+
     let mut solution_file = fs::File::create("solutions/day09_solution.txt")?;
     writeln!(solution_file, "Solution for Task 1 of Day 09:")?;
     writeln!(
@@ -103,43 +265,9 @@ fn task2() -> Result<(), Error> {
     println!("Computing solution for task 2 of Day 9...");
 
     let input_data = fs::read_to_string("input_data/day09_input.txt")?;
-    let diskmap: Vec<u32> = input_data
-        .chars()
-        .map(|c| {
-            c.to_digit(10)
-                .expect("Input string contains non-digit characters")
-        })
-        .collect();
-
-    let (mut empty_blocks, file_locations) = parse_diskmap(diskmap);
-    let mut checksum: u64 = 0;
-
-    let mut file_idx = file_locations.len() - 1;
-
-    while file_idx > 0 {
-        let file_block = file_locations[file_idx];
-        let file_length = file_block.end - file_block.start;
-
-        for empty_block in empty_blocks.iter_mut() {
-            if empty_block.start >= file_block.end {
-                // file stays in same location
-                checksum += file_idx as u64
-                    * (((file_block.start + file_block.end - 1)
-                        * (file_block.end - file_block.start))
-                        / 2);
-                break;
-            }
-            if empty_block.end - empty_block.start >= file_length {
-                checksum += file_idx as u64
-                    * (((empty_block.start + empty_block.start + file_length - 1) * file_length)
-                        / 2);
-                empty_block.start += file_length;
-                break;
-            }
-        }
-
-        file_idx -= 1;
-    }
+    let mut disk = Disk::from_diskmap(&parse_diskmap(&input_data));
+    disk.compact(CompactionStrategy::WholeFile);
+    let checksum = disk.checksum();
 
     let mut solution_file = fs::OpenOptions::new()
         .append(true)
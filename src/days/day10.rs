@@ -1,8 +1,8 @@
 use std::collections::HashSet;
 use std::fs;
 use std::hash::Hash;
-use std::io::Error;
 use std::io::Write;
+use std::io::{Error, ErrorKind};
 
 #[derive(Eq, Hash, PartialEq, Clone, Copy)]
 struct Location {
@@ -32,90 +32,276 @@ fn parse_map(input_data: String) -> (Vec<Vec<u32>>, HashSet<Location>, usize, us
     (map, trailheads, height + 1, width + 1)
 }
 
-pub fn run() -> Result<(), Error> {
-    let _ = task1();
-    let _ = task2();
-
-    println!("Completed solutions for Day 10!");
+/// The rules for a hiking trail: it starts at height 0, every step goes up by exactly `step` and
+/// it ends on a peak of height `max_height`.
+#[derive(Clone, Copy)]
+struct TrailRules {
+    step: u32,
+    max_height: u32,
+}
 
-    Ok(())
+/// A set of peaks, stored as a bitset over the peak indices.
+#[derive(Clone)]
+struct PeakSet {
+    words: Vec<u64>,
 }
+impl PeakSet {
+    pub fn new(num_peaks: usize) -> PeakSet {
+        PeakSet {
+            words: vec![0; num_peaks.div_ceil(64)],
+        }
+    }
 
-fn task1() -> Result<(), Error> {
-    println!("Computing solution for task 1 of Day 10...");
+    pub fn insert(&mut self, peak_idx: usize) {
+        self.words[peak_idx / 64] |= 1 << (peak_idx % 64);
+    }
 
-    let input_data = fs::read_to_string("input_data/day10_input.txt")?;
+    pub fn union_with(&mut self, other: &PeakSet) {
+        for (word, other_word) in self.words.iter_mut().zip(other.words.iter()) {
+            *word |= other_word;
+        }
+    }
 
-    let (map, trailheads, height, width) = parse_map(input_data);
+    pub fn len(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+}
 
-    let mut sum_trailhead_scores = 0;
-    for &trailhead in trailheads.iter() {
-        let mut reachable_peaks: HashSet<Location> = HashSet::new();
+impl TrailRules {
+    fn validate(&self) -> Result<(), Error> {
+        if self.step == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Trails must climb by a step size of at least 1",
+            ));
+        }
+        Ok(())
+    }
+}
 
-        let mut to_visit: HashSet<(u32, Location)> = HashSet::from([(0, trailhead)]);
+const PUZZLE_RULES: TrailRules = TrailRules {
+    step: 1,
+    max_height: 9,
+};
+
+struct TrailheadSummary {
+    trailhead: Location,
+    // the number of distinct peaks reachable from the trailhead
+    score: usize,
+    // the number of distinct trails starting at the trailhead
+    rating: u64,
+}
 
-        while let Some((current_height, current_loc)) = to_visit.iter().next().cloned() {
-            to_visit.remove(&(current_height, current_loc));
+fn neighbours(location: Location, height: usize, width: usize) -> Vec<Location> {
+    let mut neighbours = Vec::with_capacity(4);
+    if location.row > 0 {
+        neighbours.push(Location {
+            row: location.row - 1,
+            col: location.col,
+        });
+    }
+    if location.row < height - 1 {
+        neighbours.push(Location {
+            row: location.row + 1,
+            col: location.col,
+        });
+    }
+    if location.col > 0 {
+        neighbours.push(Location {
+            row: location.row,
+            col: location.col - 1,
+        });
+    }
+    if location.col < width - 1 {
+        neighbours.push(Location {
+            row: location.row,
+            col: location.col + 1,
+        });
+    }
+    neighbours
+}
 
-            if current_height == 9 {
-                reachable_peaks.insert(current_loc);
-                continue;
+/// Computes the score and rating of every trailhead in a single pass. Cells are processed from
+/// the peaks downwards, so the reachable peaks and the number of trails to a peak of every cell
+/// are known once the cells one step higher have been processed.
+fn analyse_trails(
+    map: &[Vec<u32>],
+    trailheads: &HashSet<Location>,
+    rules: TrailRules,
+) -> Result<Vec<TrailheadSummary>, Error> {
+    rules.validate()?;
+
+    let height = map.len();
+    let width = map.first().map_or(0, |row| row.len());
+
+    // only heights that can be reached from 0 in whole steps are part of any trail
+    let mut cells_by_level: Vec<Vec<Location>> =
+        vec![Vec::new(); (rules.max_height / rules.step) as usize + 1];
+    for (row, line) in map.iter().enumerate() {
+        for (col, &cell_height) in line.iter().enumerate() {
+            if cell_height <= rules.max_height && cell_height.is_multiple_of(rules.step) {
+                cells_by_level[(cell_height / rules.step) as usize].push(Location { row, col });
+            }
+        }
+    }
+    let num_peaks = if rules.max_height.is_multiple_of(rules.step) {
+        cells_by_level.last().map_or(0, |peaks| peaks.len())
+    } else {
+        0
+    };
+
+    let mut reachable_peaks: Vec<Vec<Option<PeakSet>>> = vec![vec![None; width]; height];
+    let mut num_trails: Vec<Vec<u64>> = vec![vec![0; width]; height];
+
+    for (level, cells) in cells_by_level.iter().enumerate().rev() {
+        let cell_height = level as u32 * rules.step;
+        for (cell_idx, &cell) in cells.iter().enumerate() {
+            let mut cell_peaks = PeakSet::new(num_peaks);
+            let mut cell_trails = 0;
+
+            if cell_height == rules.max_height {
+                cell_peaks.insert(cell_idx);
+                cell_trails = 1;
+            } else {
+                for neighbour in neighbours(cell, height, width) {
+                    if map[neighbour.row][neighbour.col] != cell_height + rules.step {
+                        continue;
+                    }
+                    if let Some(neighbour_peaks) = &reachable_peaks[neighbour.row][neighbour.col] {
+                        cell_peaks.union_with(neighbour_peaks);
+                    }
+                    cell_trails += num_trails[neighbour.row][neighbour.col];
+                }
             }
 
-            let current_row = current_loc.row;
-            let current_col = current_loc.col;
-
-            // Up
-            if (current_row > 0) && (map[current_row - 1][current_col] == current_height + 1) {
-                to_visit.insert((
-                    current_height + 1,
-                    Location {
-                        row: current_row - 1,
-                        col: current_col,
-                    },
-                ));
+            reachable_peaks[cell.row][cell.col] = Some(cell_peaks);
+            num_trails[cell.row][cell.col] = cell_trails;
+        }
+        // the sets two levels up are not needed anymore
+        if let Some(old_cells) = cells_by_level.get(level + 2) {
+            for old_cell in old_cells {
+                reachable_peaks[old_cell.row][old_cell.col] = None;
             }
+        }
+    }
+
+    let mut summaries: Vec<TrailheadSummary> = trailheads
+        .iter()
+        .map(|&trailhead| TrailheadSummary {
+            trailhead,
+            score: reachable_peaks[trailhead.row][trailhead.col]
+                .as_ref()
+                .map_or(0, |peaks| peaks.len()),
+            rating: num_trails[trailhead.row][trailhead.col],
+        })
+        .collect();
+    summaries.sort_by_key(|summary| (summary.trailhead.row, summary.trailhead.col));
+
+    Ok(summaries)
+}
 
-            // Down
-            if (current_row < height - 1)
-                && (map[current_row + 1][current_col] == current_height + 1)
-            {
-                to_visit.insert((
-                    current_height + 1,
-                    Location {
-                        row: current_row + 1,
-                        col: current_col,
-                    },
-                ));
+/// Lazily enumerates the distinct trails starting at a trailhead, depth first.
+struct TrailIter<'a> {
+    map: &'a [Vec<u32>],
+    rules: TrailRules,
+    // partial trails that still have to be extended
+    to_extend: Vec<Vec<Location>>,
+}
+impl Iterator for TrailIter<'_> {
+    type Item = Vec<Location>;
+
+    fn next(&mut self) -> Option<Vec<Location>> {
+        let height = self.map.len();
+        let width = self.map[0].len();
+
+        while let Some(trail) = self.to_extend.pop() {
+            let current_loc = *trail.last().unwrap();
+            let current_height = self.map[current_loc.row][current_loc.col];
+            if current_height == self.rules.max_height {
+                return Some(trail);
             }
 
-            // Left
-            if (current_col > 0) && (map[current_row][current_col - 1] == current_height + 1) {
-                to_visit.insert((
-                    current_height + 1,
-                    Location {
-                        row: current_row,
-                        col: current_col - 1,
-                    },
-                ));
+            // pushed in reverse so that trails are returned in the order of `neighbours`
+            for neighbour in neighbours(current_loc, height, width).into_iter().rev() {
+                if self.map[neighbour.row][neighbour.col] == current_height + self.rules.step {
+                    let mut extended_trail = trail.clone();
+                    extended_trail.push(neighbour);
+                    self.to_extend.push(extended_trail);
+                }
             }
+        }
+
+        None
+    }
+}
 
-            // Right
-            if (current_col < width - 1)
-                && (map[current_row][current_col + 1] == current_height + 1)
-            {
-                to_visit.insert((
-                    current_height + 1,
-                    Location {
-                        row: current_row,
-                        col: current_col + 1,
-                    },
-                ));
+/// Returns the trails starting at `trailhead` as lists of locations, stopping after `limit`
+/// trails if one is given.
+fn enumerate_trails(
+    map: &[Vec<u32>],
+    trailhead: Location,
+    rules: TrailRules,
+    limit: Option<usize>,
+) -> Result<impl Iterator<Item = Vec<Location>> + '_, Error> {
+    rules.validate()?;
+
+    let to_extend = if map[trailhead.row][trailhead.col] == 0 {
+        vec![vec![trailhead]]
+    } else {
+        Vec::new()
+    };
+
+    Ok(TrailIter {
+        map,
+        rules,
+        to_extend,
+    }
+    .take(limit.unwrap_or(usize::MAX)))
+}
+
+/// Renders the map with only the heights along the trail shown, like the puzzle's examples.
+fn render_trail(map: &[Vec<u32>], trail: &[Location]) -> String {
+    let trail_locations: HashSet<&Location> = trail.iter().collect();
+
+    let mut rendered = String::new();
+    for (row, line) in map.iter().enumerate() {
+        for (col, cell_height) in line.iter().enumerate() {
+            if trail_locations.contains(&Location { row, col }) {
+                rendered.push(char::from_digit(*cell_height, 10).unwrap_or('?'));
+            } else {
+                rendered.push('.');
             }
         }
-        sum_trailhead_scores += reachable_peaks.len();
+        rendered.push('\n');
     }
 
+    rendered
+}
+
+pub fn run() -> Result<(), Error> {
+    let _ = task1();
+    let _ = task2();
+
+    println!("Completed solutions for Day 10!");
+
+    Ok(())
+}
+
+fn task1() -> Result<(), Error> {
+    println!("Computing solution for task 1 of Day 10...");
+
+    let input_data = fs::read_to_string("input_data/day10_input.txt")?;
+
+    let (map, trailheads, _, _) = parse_map(input_data);
+
+    let sum_trailhead_scores: usize = analyse_trails(&map, &trailheads, PUZZLE_RULES)?
+        .iter()
+        .map(|summary| summary.score)
+        .sum();
+
     let mut solution_file = fs::File::create("solutions/day10_solution.txt")?;
     writeln!(solution_file, "Solution for Task 1 of Day 10:")?;
     writeln!(
@@ -132,73 +318,29 @@ fn task2() -> Result<(), Error> {
 
     let input_data = fs::read_to_string("input_data/day10_input.txt")?;
 
-    let (map, trailheads, height, width) = parse_map(input_data);
-
-    let mut sum_trailhead_ratings = 0;
-
-    for &trailhead in trailheads.iter() {
-        let mut current_rating: u32 = 0;
-
-        let mut to_visit: Vec<(u32, Location)> = vec![(0, trailhead)];
-
-        while let Some((current_height, current_loc)) = to_visit.pop() {
-            if current_height == 9 {
-                current_rating += 1;
-                continue;
-            }
-
-            let current_row = current_loc.row;
-            let current_col = current_loc.col;
-
-            // Up
-            if (current_row > 0) && (map[current_row - 1][current_col] == current_height + 1) {
-                to_visit.push((
-                    current_height + 1,
-                    Location {
-                        row: current_row - 1,
-                        col: current_col,
-                    },
-                ));
-            }
-
-            // Down
-            if (current_row < height - 1)
-                && (map[current_row + 1][current_col] == current_height + 1)
-            {
-                to_visit.push((
-                    current_height + 1,
-                    Location {
-                        row: current_row + 1,
-                        col: current_col,
-                    },
-                ));
-            }
-
-            // Left
-            if (current_col > 0) && (map[current_row][current_col - 1] == current_height + 1) {
-                to_visit.push((
-                    current_height + 1,
-                    Location {
-                        row: current_row,
-                        col: current_col - 1,
-                    },
-                ));
-            }
-
-            // Right
-            if (current_col < width - 1)
-                && (map[current_row][current_col + 1] == current_height + 1)
-            {
-                to_visit.push((
-                    current_height + 1,
-                    Location {
-                        row: current_row,
-                        col: current_col + 1,
-                    },
-                ));
-            }
+    let (map, trailheads, _, _) = parse_map(input_data);
+
+    let trailhead_summaries = analyse_trails(&map, &trailheads, PUZZLE_RULES)?;
+    let sum_trailhead_ratings: u64 = trailhead_summaries
+        .iter()
+        .map(|summary| summary.rating)
+        .sum();
+
+    // export a few of the trails of the best rated trailhead
+    if let Some(best_summary) = trailhead_summaries
+        .iter()
+        .max_by_key(|summary| summary.rating)
+    {
+        let mut trails_file = fs::File::create("solutions/day10_task2_trails.txt")?;
+        writeln!(
+            trails_file,
+            "The trailhead at row {}, column {} has a rating of {}. Its first trails are:",
+            best_summary.trailhead.row, best_summary.trailhead.col, best_summary.rating
+        )?;
+        for trail in enumerate_trails(&map, best_summary.trailhead, PUZZLE_RULES, Some(3))? {
+            writeln!(trails_file)?;
+            writeln!(trails_file, "{}", render_trail(&map, &trail))?;
         }
-        sum_trailhead_ratings += current_rating;
     }
 
     let mut solution_file = fs::OpenOptions::new()
@@ -215,3 +357,24 @@ fn task2() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_step_is_an_error() {
+        let (map, trailheads, _, _) = parse_map("0123\n1234\n8765\n9876".to_string());
+        let rules = TrailRules {
+            step: 0,
+            max_height: 9,
+        };
+
+        assert!(analyse_trails(&map, &trailheads, rules).is_err());
+        assert!(enumerate_trails(&map, Location { row: 0, col: 0 }, rules, None).is_err());
+        assert_eq!(
+            analyse_trails(&map, &trailheads, PUZZLE_RULES).unwrap()[0].score,
+            1
+        );
+    }
+}
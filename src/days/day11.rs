@@ -3,12 +3,12 @@ use std::fs;
 use std::io::Error;
 use std::io::Write;
 
-fn parse_stones(input_data: String) -> HashMap<u64, u64> {
+fn parse_stones(input_data: String) -> HashMap<u64, u128> {
     let stone_list: Vec<u64> = input_data
         .split_whitespace()
         .filter_map(|s| s.parse::<u64>().ok())
         .collect();
-    let mut stones: HashMap<u64, u64> = HashMap::new();
+    let mut stones: HashMap<u64, u128> = HashMap::new();
 
     for &stone in stone_list.iter() {
         *stones.entry(stone).or_insert(0) += 1;
@@ -17,25 +17,87 @@ fn parse_stones(input_data: String) -> HashMap<u64, u64> {
     stones
 }
 
-fn update_stones(stones: HashMap<u64, u64>) -> HashMap<u64, u64> {
-    let mut new_stones = HashMap::new();
-
-    for (stone, num_occurrences) in stones {
-        if stone == 0 {
-            *new_stones.entry(1).or_insert(0) += num_occurrences;
-        } else {
-            let num_digits = stone.ilog(10) + 1;
-            if num_digits % 2 == 0 {
-                let basis = 10_u64.pow(num_digits / 2);
-                *new_stones.entry(stone / basis).or_insert(0) += num_occurrences;
-                *new_stones.entry(stone % basis).or_insert(0) += num_occurrences;
-            } else {
-                *new_stones.entry(stone * 2024).or_insert(0) += num_occurrences;
+/// The rules from the puzzle: 0 becomes 1, numbers with an even number of digits split into two
+/// halves, everything else is multiplied by 2024.
+fn puzzle_rule(stone: u64) -> Vec<u64> {
+    if stone == 0 {
+        return vec![1];
+    }
+    let num_digits = stone.ilog(10) + 1;
+    if num_digits.is_multiple_of(2) {
+        let basis = 10_u64.pow(num_digits / 2);
+        vec![stone / basis, stone % basis]
+    } else {
+        vec![stone * 2024]
+    }
+}
+
+/// Simulates blinking at stones, with `rule` giving the stones a single stone turns into.
+struct BlinkSimulator<F: Fn(u64) -> Vec<u64>> {
+    rule: F,
+    // number of stones a single stone turns into after a number of blinks
+    known_counts: HashMap<(u64, u32), u128>,
+}
+impl<F: Fn(u64) -> Vec<u64>> BlinkSimulator<F> {
+    pub fn new(rule: F) -> BlinkSimulator<F> {
+        BlinkSimulator {
+            rule,
+            known_counts: HashMap::new(),
+        }
+    }
+
+    /// The number of stones a single stone turns into after `num_blinks` blinks.
+    pub fn count_after(&mut self, stone: u64, num_blinks: u32) -> u128 {
+        if num_blinks == 0 {
+            return 1;
+        }
+        if let Some(&count) = self.known_counts.get(&(stone, num_blinks)) {
+            return count;
+        }
+
+        let mut count = 0;
+        for new_stone in (self.rule)(stone) {
+            count += self.count_after(new_stone, num_blinks - 1);
+        }
+        self.known_counts.insert((stone, num_blinks), count);
+
+        count
+    }
+
+    pub fn total_count_after(&mut self, stones: &HashMap<u64, u128>, num_blinks: u32) -> u128 {
+        stones
+            .iter()
+            .map(|(&stone, &num_occurrences)| num_occurrences * self.count_after(stone, num_blinks))
+            .sum()
+    }
+
+    /// Blinks once, keeping track of how often each stone occurs.
+    pub fn blink(&self, stones: &HashMap<u64, u128>) -> HashMap<u64, u128> {
+        let mut new_stones = HashMap::new();
+
+        for (&stone, &num_occurrences) in stones {
+            for new_stone in (self.rule)(stone) {
+                *new_stones.entry(new_stone).or_insert(0) += num_occurrences;
             }
         }
+
+        new_stones
     }
 
-    new_stones
+    /// The number of different stones after each blink, starting with the initial stones.
+    pub fn distinct_stones_per_blink(
+        &self,
+        stones: &HashMap<u64, u128>,
+        num_blinks: u32,
+    ) -> Vec<usize> {
+        let mut stones = stones.clone();
+        let mut distinct_stones = vec![stones.len()];
+        for _ in 0..num_blinks {
+            stones = self.blink(&stones);
+            distinct_stones.push(stones.len());
+        }
+        distinct_stones
+    }
 }
 
 pub fn run() -> Result<(), Error> {
@@ -52,15 +114,9 @@ fn task1() -> Result<(), Error> {
 
     let input_data = fs::read_to_string("input_data/day11_input.txt")?;
 
-    let mut stones = parse_stones(input_data);
-
-    for _ in 0..25 {
-        stones = update_stones(stones);
-    }
-    let mut num_stones: u64 = 0;
-    for (_, num_occurrences) in stones {
-        num_stones += num_occurrences;
-    }
+    let stones = parse_stones(input_data);
+    let mut simulator = BlinkSimulator::new(puzzle_rule);
+    let num_stones = simulator.total_count_after(&stones, 25);
 
     let mut solution_file = fs::File::create("solutions/day11_solution.txt")?;
     writeln!(solution_file, "Solution for Task 1 of Day 11:")?;
@@ -78,14 +134,22 @@ fn task2() -> Result<(), Error> {
 
     let input_data = fs::read_to_string("input_data/day11_input.txt")?;
 
-    let mut stones = parse_stones(input_data);
-
-    for _ in 0..75 {
-        stones = update_stones(stones);
-    }
-    let mut num_stones: u64 = 0;
-    for (_, num_occurrences) in stones {
-        num_stones += num_occurrences;
+    let stones = parse_stones(input_data);
+    let mut simulator = BlinkSimulator::new(puzzle_rule);
+    let num_stones = simulator.total_count_after(&stones, 75);
+
+    // the number of different stones levels off quickly, which is what keeps this tractable
+    let mut distinct_stones_file = fs::File::create("solutions/day11_distinct_stones.txt")?;
+    for (num_blinks, num_distinct_stones) in simulator
+        .distinct_stones_per_blink(&stones, 75)
+        .iter()
+        .enumerate()
+    {
+        writeln!(
+            distinct_stones_file,
+            "After {} blinks: {} different stones",
+            num_blinks, num_distinct_stones
+        )?;
     }
 
     let mut solution_file = fs::OpenOptions::new()
@@ -1,4 +1,3 @@
-use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::Error;
 use std::io::Write;
@@ -9,267 +8,382 @@ struct Location {
     col: usize,
 }
 
-pub fn run() -> Result<(), Error> {
-    let _ = task1();
-    let _ = task2();
+/// The smallest rectangle containing a region, with inclusive bounds.
+#[derive(Clone, Copy)]
+struct BoundingBox {
+    min_row: usize,
+    max_row: usize,
+    min_col: usize,
+    max_col: usize,
+}
 
-    println!("Completed solutions for Day 12!");
+struct Region<T> {
+    plant: T,
+    cells: Vec<Location>,
+    perimeter: usize,
+    num_sides: usize,
+    bounding_box: BoundingBox,
+    // the number of separate areas of other plants that are completely enclosed by the region
+    num_holes: usize,
+}
+impl<T> Region<T> {
+    pub fn area(&self) -> usize {
+        self.cells.len()
+    }
 
-    Ok(())
+    pub fn price(&self) -> usize {
+        self.area() * self.perimeter
+    }
+
+    pub fn bulk_discount_price(&self) -> usize {
+        self.area() * self.num_sides
+    }
 }
 
-fn task1() -> Result<(), Error> {
-    println!("Computing solution for task 1 of Day 12...");
+struct RegionMap<T> {
+    // the index into `regions` for every location on the map
+    labels: Vec<Vec<usize>>,
+    regions: Vec<Region<T>>,
+}
 
-    let map: Vec<Vec<char>> = fs::read_to_string("input_data/day12_input.txt")?
-        .lines()
-        .map(|line| line.chars().collect())
-        .collect();
+const NEIGHBOUR_OFFSETS: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+fn offset_location(
+    location: Location,
+    (row_offset, col_offset): (isize, isize),
+    height: usize,
+    width: usize,
+) -> Option<Location> {
+    let row = location.row.checked_add_signed(row_offset)?;
+    let col = location.col.checked_add_signed(col_offset)?;
+    if row < height && col < width {
+        Some(Location { row, col })
+    } else {
+        None
+    }
+}
 
+/// Splits a map into regions of horizontally or vertically connected equal cells, and computes
+/// the properties of every region.
+fn find_regions<T: Copy + PartialEq>(map: &[Vec<T>]) -> RegionMap<T> {
     let height = map.len();
-    let width = map.first().unwrap().len();
+    let width = map.first().map_or(0, |line| line.len());
 
-    let mut total_price: u32 = 0;
-    let mut checked_locs: HashSet<Location> = HashSet::new();
+    let mut labels = vec![vec![usize::MAX; width]; height];
+    let mut region_cells: Vec<Vec<Location>> = Vec::new();
 
-    for (row, line) in map.iter().enumerate() {
-        for (col, plant) in line.iter().enumerate() {
-            if checked_locs.contains(&Location { row, col }) {
+    for row in 0..height {
+        for col in 0..width {
+            if labels[row][col] != usize::MAX {
                 continue;
             }
-
-            let mut area: u32 = 0;
-            let mut perimeter: u32 = 0;
-            let mut to_check: HashSet<Location> = HashSet::from([Location { row, col }]);
-
-            while let Some(current_location) = to_check.iter().next().cloned() {
-                to_check.remove(&current_location);
-                if checked_locs.contains(&current_location) {
-                    continue;
-                }
-
-                // Up
-                if current_location.row > 0
-                    && map[current_location.row - 1][current_location.col] == *plant
-                {
-                    to_check.insert(Location {
-                        row: current_location.row - 1,
-                        col: current_location.col,
-                    });
-                } else {
-                    perimeter += 1;
+            let region_id = region_cells.len();
+            let plant = map[row][col];
+
+            let mut cells = Vec::new();
+            let mut to_check = vec![Location { row, col }];
+            labels[row][col] = region_id;
+            while let Some(current_location) = to_check.pop() {
+                cells.push(current_location);
+                for offset in NEIGHBOUR_OFFSETS {
+                    if let Some(neighbour) =
+                        offset_location(current_location, offset, height, width)
+                    {
+                        if labels[neighbour.row][neighbour.col] == usize::MAX
+                            && map[neighbour.row][neighbour.col] == plant
+                        {
+                            labels[neighbour.row][neighbour.col] = region_id;
+                            to_check.push(neighbour);
+                        }
+                    }
                 }
+            }
+            region_cells.push(cells);
+        }
+    }
 
-                // Down
-                if current_location.row < height - 1
-                    && map[current_location.row + 1][current_location.col] == *plant
-                {
-                    to_check.insert(Location {
-                        row: current_location.row + 1,
-                        col: current_location.col,
-                    });
-                } else {
-                    perimeter += 1;
-                }
+    let regions = region_cells
+        .into_iter()
+        .enumerate()
+        .map(|(region_id, cells)| {
+            let first_cell = cells[0];
+            let bounding_box = cells.iter().fold(
+                BoundingBox {
+                    min_row: first_cell.row,
+                    max_row: first_cell.row,
+                    min_col: first_cell.col,
+                    max_col: first_cell.col,
+                },
+                |bounding_box, cell| BoundingBox {
+                    min_row: bounding_box.min_row.min(cell.row),
+                    max_row: bounding_box.max_row.max(cell.row),
+                    min_col: bounding_box.min_col.min(cell.col),
+                    max_col: bounding_box.max_col.max(cell.col),
+                },
+            );
+
+            Region {
+                plant: map[first_cell.row][first_cell.col],
+                perimeter: count_perimeter(&labels, region_id, &cells),
+                num_sides: count_corners(&labels, region_id, &cells),
+                num_holes: count_holes(&labels, region_id, bounding_box),
+                bounding_box,
+                cells,
+            }
+        })
+        .collect();
 
-                // Left
-                if current_location.col > 0
-                    && map[current_location.row][current_location.col - 1] == *plant
-                {
-                    to_check.insert(Location {
-                        row: current_location.row,
-                        col: current_location.col - 1,
-                    });
-                } else {
-                    perimeter += 1;
-                }
+    RegionMap { labels, regions }
+}
 
-                // Right
-                if current_location.col < width - 1
-                    && map[current_location.row][current_location.col + 1] == *plant
-                {
-                    to_check.insert(Location {
-                        row: current_location.row,
-                        col: current_location.col + 1,
-                    });
-                } else {
-                    perimeter += 1;
-                }
+/// Renders the map with the region ID in every cell and fences drawn between cells of different
+/// regions, e.g.
+/// ```text
+/// +---+---+
+/// | 0   0 |
+/// +   +---+
+/// | 0 | 1 |
+/// +---+---+
+/// ```
+fn render_fences<T>(region_map: &RegionMap<T>) -> String {
+    let labels = &region_map.labels;
+    let height = labels.len();
+    let width = labels.first().map_or(0, |line| line.len());
+    let cell_width = region_map.regions.len().max(1).to_string().len() + 2;
+
+    // None is outside the map, so the map is fenced in
+    let label_at = |row: isize, col: isize| -> Option<usize> {
+        if row < 0 || col < 0 {
+            return None;
+        }
+        labels
+            .get(row as usize)
+            .and_then(|line| line.get(col as usize))
+            .copied()
+    };
+
+    let mut rendered = String::new();
+    for row in 0..=height as isize {
+        // the line of fences above the row
+        for col in 0..=width as isize {
+            let fence_left = label_at(row - 1, col - 1) != label_at(row, col - 1);
+            let fence_right = label_at(row - 1, col) != label_at(row, col);
+            let fence_up = label_at(row - 1, col - 1) != label_at(row - 1, col);
+            let fence_down = label_at(row, col - 1) != label_at(row, col);
+            if fence_left || fence_right || fence_up || fence_down {
+                rendered.push('+');
+            } else {
+                rendered.push(' ');
+            }
+            if col < width as isize {
+                let segment = if fence_right { '-' } else { ' ' };
+                rendered.extend(std::iter::repeat_n(segment, cell_width));
+            }
+        }
+        rendered.push('\n');
 
-                area += 1;
-                checked_locs.insert(current_location);
+        if row == height as isize {
+            break;
+        }
+        for col in 0..=width as isize {
+            if label_at(row, col - 1) != label_at(row, col) {
+                rendered.push('|');
+            } else {
+                rendered.push(' ');
+            }
+            if let Some(label) = label_at(row, col) {
+                rendered.push_str(&format!("{:^cell_width$}", label));
             }
-            total_price += area * perimeter;
         }
+        rendered.push('\n');
     }
 
-    let mut solution_file = fs::File::create("solutions/day12_solution.txt")?;
-    writeln!(solution_file, "Solution for Task 1 of Day 12:")?;
-    writeln!(
-        solution_file,
-        "The total price for all fences is {}.",
-        total_price
-    )?;
+    rendered
+}
+
+/// Writes one line per region with its properties and both prices, most expensive first.
+fn regions_to_csv<T: std::fmt::Display>(regions: &[Region<T>]) -> String {
+    let mut sorted_ids: Vec<usize> = (0..regions.len()).collect();
+    sorted_ids.sort_by_key(|&region_id| (std::cmp::Reverse(regions[region_id].price()), region_id));
+
+    let mut csv = String::from("region_id,plant,area,perimeter,sides,price,bulk_discount_price\n");
+    for region_id in sorted_ids {
+        let region = &regions[region_id];
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            region_id,
+            region.plant,
+            region.area(),
+            region.perimeter,
+            region.num_sides,
+            region.price(),
+            region.bulk_discount_price()
+        ));
+    }
+
+    csv
+}
+
+fn write_fence_report() -> Result<(), Error> {
+    println!("Writing fence report for Day 12...");
+
+    let region_map = find_regions(&read_map()?);
+
+    let mut map_file = fs::File::create("solutions/day12_fence_map.txt")?;
+    write!(map_file, "{}", render_fences(&region_map))?;
+
+    let mut csv_file = fs::File::create("solutions/day12_regions.csv")?;
+    write!(csv_file, "{}", regions_to_csv(&region_map.regions))?;
 
     Ok(())
 }
 
-fn task2() -> Result<(), Error> {
-    println!("Computing solution for task 2 of Day 12...");
+fn is_in_region(labels: &[Vec<usize>], region_id: usize, row: isize, col: isize) -> bool {
+    if row < 0 || col < 0 {
+        return false;
+    }
+    labels
+        .get(row as usize)
+        .and_then(|line| line.get(col as usize))
+        .is_some_and(|&label| label == region_id)
+}
 
-    let map: Vec<Vec<char>> = fs::read_to_string("input_data/day12_input.txt")?
-        .lines()
-        .map(|line| line.chars().collect())
-        .collect();
+fn count_perimeter(labels: &[Vec<usize>], region_id: usize, cells: &[Location]) -> usize {
+    let mut perimeter = 0;
+    for cell in cells {
+        for (row_offset, col_offset) in NEIGHBOUR_OFFSETS {
+            if !is_in_region(
+                labels,
+                region_id,
+                cell.row as isize + row_offset,
+                cell.col as isize + col_offset,
+            ) {
+                perimeter += 1;
+            }
+        }
+    }
+    perimeter
+}
 
-    let height = map.len();
-    let width = map.first().unwrap().len();
+/// A polygon has as many sides as corners, so the sides of a region are counted by looking at
+/// the four corners of every cell.
+fn count_corners(labels: &[Vec<usize>], region_id: usize, cells: &[Location]) -> usize {
+    let mut num_corners = 0;
+    for cell in cells {
+        let (row, col) = (cell.row as isize, cell.col as isize);
+        for (row_offset, col_offset) in [(-1, -1), (-1, 1), (1, -1), (1, 1)] {
+            let vertical = is_in_region(labels, region_id, row + row_offset, col);
+            let horizontal = is_in_region(labels, region_id, row, col + col_offset);
+            let diagonal = is_in_region(labels, region_id, row + row_offset, col + col_offset);
+
+            // outer corner, or inner corner of an L shape
+            if (!vertical && !horizontal) || (vertical && horizontal && !diagonal) {
+                num_corners += 1;
+            }
+        }
+    }
+    num_corners
+}
 
-    let mut total_price: u32 = 0;
-    let mut checked_locs: HashSet<Location> = HashSet::new();
-    for (row, line) in map.iter().enumerate() {
-        for (col, plant) in line.iter().enumerate() {
-            if checked_locs.contains(&Location { row, col }) {
+/// Counts the horizontally or vertically connected areas of other cells inside the bounding box
+/// that cannot reach the outside of the bounding box without crossing the region.
+fn count_holes(labels: &[Vec<usize>], region_id: usize, bounding_box: BoundingBox) -> usize {
+    // the bounding box with a margin of one cell on each side, so the outside is connected
+    let box_height = bounding_box.max_row - bounding_box.min_row + 3;
+    let box_width = bounding_box.max_col - bounding_box.min_col + 3;
+    let is_region_cell = |box_location: Location| {
+        is_in_region(
+            labels,
+            region_id,
+            (box_location.row + bounding_box.min_row) as isize - 1,
+            (box_location.col + bounding_box.min_col) as isize - 1,
+        )
+    };
+
+    let mut visited = vec![vec![false; box_width]; box_height];
+    let mut num_components = 0;
+    for row in 0..box_height {
+        for col in 0..box_width {
+            if visited[row][col] || is_region_cell(Location { row, col }) {
                 continue;
             }
-            let mut region_locations: HashSet<Location> = HashSet::from([Location { row, col }]);
+            num_components += 1;
+            visited[row][col] = true;
+            let mut to_check = vec![Location { row, col }];
+            while let Some(current_location) = to_check.pop() {
+                for offset in NEIGHBOUR_OFFSETS {
+                    if let Some(neighbour) =
+                        offset_location(current_location, offset, box_height, box_width)
+                    {
+                        if !visited[neighbour.row][neighbour.col] && !is_region_cell(neighbour) {
+                            visited[neighbour.row][neighbour.col] = true;
+                            to_check.push(neighbour);
+                        }
+                    }
+                }
+            }
+        }
+    }
 
-            let mut area: u32 = 0;
-            let mut locs_to_check: HashSet<Location> = HashSet::from([Location { row, col }]);
+    // the first component found contains the margin, i.e. the outside
+    num_components - 1
+}
 
-            // key row, val col
-            let mut region_up_edges: HashMap<usize, Vec<usize>> = HashMap::new();
-            let mut region_down_edges: HashMap<usize, Vec<usize>> = HashMap::new();
+fn read_map() -> Result<Vec<Vec<char>>, Error> {
+    Ok(fs::read_to_string("input_data/day12_input.txt")?
+        .lines()
+        .map(|line| line.chars().collect())
+        .collect())
+}
 
-            // key col, val row
-            let mut region_left_edges: HashMap<usize, Vec<usize>> = HashMap::new();
-            let mut region_right_edges: HashMap<usize, Vec<usize>> = HashMap::new();
+pub fn run() -> Result<(), Error> {
+    let _ = task1();
+    let _ = task2();
+    let _ = write_fence_report();
 
-            while let Some(current_location) = locs_to_check.iter().next().cloned() {
-                locs_to_check.remove(&current_location);
-                if checked_locs.contains(&current_location) {
-                    continue;
-                }
+    println!("Completed solutions for Day 12!");
 
-                // Up
-                if current_location.row > 0
-                    && map[current_location.row - 1][current_location.col] == *plant
-                {
-                    locs_to_check.insert(Location {
-                        row: current_location.row - 1,
-                        col: current_location.col,
-                    });
-                } else {
-                    region_up_edges
-                        .entry(current_location.row)
-                        .or_default()
-                        .push(current_location.col);
-                }
+    Ok(())
+}
 
-                // Down
-                if current_location.row < height - 1
-                    && map[current_location.row + 1][current_location.col] == *plant
-                {
-                    locs_to_check.insert(Location {
-                        row: current_location.row + 1,
-                        col: current_location.col,
-                    });
-                } else {
-                    region_down_edges
-                        .entry(current_location.row)
-                        .or_default()
-                        .push(current_location.col);
-                }
+fn task1() -> Result<(), Error> {
+    println!("Computing solution for task 1 of Day 12...");
 
-                // Left
-                if current_location.col > 0
-                    && map[current_location.row][current_location.col - 1] == *plant
-                {
-                    locs_to_check.insert(Location {
-                        row: current_location.row,
-                        col: current_location.col - 1,
-                    });
-                } else {
-                    region_left_edges
-                        .entry(current_location.col)
-                        .or_default()
-                        .push(current_location.row);
-                }
+    let regions = find_regions(&read_map()?).regions;
+    let total_price: usize = regions.iter().map(Region::price).sum();
 
-                // Right
-                if current_location.col < width - 1
-                    && map[current_location.row][current_location.col + 1] == *plant
-                {
-                    locs_to_check.insert(Location {
-                        row: current_location.row,
-                        col: current_location.col + 1,
-                    });
-                } else {
-                    region_right_edges
-                        .entry(current_location.col)
-                        .or_default()
-                        .push(current_location.row);
-                }
+    let mut solution_file = fs::File::create("solutions/day12_solution.txt")?;
+    writeln!(solution_file, "Solution for Task 1 of Day 12:")?;
+    writeln!(
+        solution_file,
+        "The total price for all fences is {}.",
+        total_price
+    )?;
 
-                area += 1;
-                checked_locs.insert(current_location);
-                region_locations.insert(current_location);
-            }
+    if let Some(largest_region) = regions.iter().max_by_key(|region| region.area()) {
+        let bounding_box = largest_region.bounding_box;
+        writeln!(
+            solution_file,
+            "The largest of the {} regions is made up of {} '{}' plants, spans rows {} to {} and columns {} to {}, and encloses {} other areas.",
+            regions.len(),
+            largest_region.area(),
+            largest_region.plant,
+            bounding_box.min_row,
+            bounding_box.max_row,
+            bounding_box.min_col,
+            bounding_box.max_col,
+            largest_region.num_holes
+        )?;
+    }
 
-            let mut num_sides: u32 = 0;
+    Ok(())
+}
 
-            // count up sides
-            for (_row, mut cols) in region_up_edges {
-                cols.sort();
-                num_sides += 1;
-                let mut prev_col = cols.first().unwrap();
-                for col in cols[1..].iter() {
-                    if col - prev_col > 1 {
-                        num_sides += 1;
-                    }
-                    prev_col = col;
-                }
-            }
-            // count down sides
-            for (_row, mut cols) in region_down_edges {
-                cols.sort();
-                num_sides += 1;
-                let mut prev_col = cols.first().unwrap();
-                for col in cols[1..].iter() {
-                    if col - prev_col > 1 {
-                        num_sides += 1;
-                    }
-                    prev_col = col;
-                }
-            }
-            // count left sides
-            for (_col, mut rows) in region_left_edges {
-                rows.sort();
-                num_sides += 1;
-                let mut prev_row = rows.first().unwrap();
-                for row in rows[1..].iter() {
-                    if row - prev_row > 1 {
-                        num_sides += 1;
-                    }
-                    prev_row = row;
-                }
-            }
-            // count right sides
-            for (_col, mut rows) in region_right_edges {
-                rows.sort();
-                num_sides += 1;
-                let mut prev_row = rows.first().unwrap();
-                for row in rows[1..].iter() {
-                    if row - prev_row > 1 {
-                        num_sides += 1;
-                    }
-                    prev_row = row;
-                }
-            }
+fn task2() -> Result<(), Error> {
+    println!("Computing solution for task 2 of Day 12...");
 
-            total_price += area * num_sides;
-        }
-    }
+    let regions = find_regions(&read_map()?).regions;
+    let total_price: usize = regions.iter().map(Region::bulk_discount_price).sum();
 
     let mut solution_file = fs::OpenOptions::new()
         .append(true)
@@ -1,3 +1,4 @@
+use std::fmt;
 use std::fs;
 use std::io::Error;
 use std::io::Write;
@@ -52,132 +53,218 @@ fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
     (old_r, old_s, old_t)
 }
 
-/// If no solution is possible, return a cost of 0.
-fn compute_lowest_price(button_a: Button, button_b: Button, target: Target) -> i64 {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum UnwinnableReason {
+    // the prize cannot be reached with any whole number of presses
+    NoIntegerSolution,
+    // the prize can only be reached by pressing a button a negative number of times
+    NeedsNegativePresses,
+    // every way of reaching the prize presses a button more often than allowed
+    ExceedsPressLimit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MachineOutcome {
+    Won {
+        presses_a: i64,
+        presses_b: i64,
+        cost: i64,
+    },
+    Unwinnable(UnwinnableReason),
+}
+impl MachineOutcome {
+    pub fn cost(&self) -> i64 {
+        match self {
+            MachineOutcome::Won { cost, .. } => *cost,
+            MachineOutcome::Unwinnable(_) => 0,
+        }
+    }
+}
+impl fmt::Display for MachineOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MachineOutcome::Won {
+                presses_a,
+                presses_b,
+                cost,
+            } => write!(
+                f,
+                "won with {} presses of A and {} presses of B for {} tokens",
+                presses_a, presses_b, cost
+            ),
+            MachineOutcome::Unwinnable(UnwinnableReason::NoIntegerSolution) => {
+                write!(
+                    f,
+                    "unwinnable, no whole number of presses reaches the prize"
+                )
+            }
+            MachineOutcome::Unwinnable(UnwinnableReason::NeedsNegativePresses) => {
+                write!(
+                    f,
+                    "unwinnable, the prize is only reached with negative presses"
+                )
+            }
+            MachineOutcome::Unwinnable(UnwinnableReason::ExceedsPressLimit) => {
+                write!(f, "unwinnable within the press limit")
+            }
+        }
+    }
+}
+
+/// How the claw machines are played: the token cost of each button, an optional limit on how
+/// often each button can be pressed, and an offset added to both prize coordinates.
+#[derive(Clone, Copy)]
+struct ClawMachineRules {
+    cost_a: i64,
+    cost_b: i64,
+    press_limit: Option<i64>,
+    prize_offset: i64,
+}
+
+struct ClawMachine {
+    button_a: Button,
+    button_b: Button,
+    target: Target,
+}
+
+/// Checks a non-negative solution against the press limit.
+fn outcome_for_presses(
+    button_a: &Button,
+    button_b: &Button,
+    presses_a: i64,
+    presses_b: i64,
+    press_limit: Option<i64>,
+) -> MachineOutcome {
+    if press_limit.is_some_and(|limit| presses_a > limit || presses_b > limit) {
+        MachineOutcome::Unwinnable(UnwinnableReason::ExceedsPressLimit)
+    } else {
+        MachineOutcome::Won {
+            presses_a,
+            presses_b,
+            cost: button_a.cost * presses_a + button_b.cost * presses_b,
+        }
+    }
+}
+
+/// Solves the case where only one button moves the claw at all, returning the number of presses.
+fn solve_single_button(button: &Button, target: &Target) -> Result<i64, UnwinnableReason> {
+    let (step, distance) = if button.x != 0 {
+        (button.x, target.x)
+    } else if button.y != 0 {
+        (button.y, target.y)
+    } else if target.x == 0 && target.y == 0 {
+        // the claw is already at the prize, solution is no presses at all
+        return Ok(0);
+    } else {
+        // neither button moves the claw
+        return Err(UnwinnableReason::NoIntegerSolution);
+    };
+
+    if distance % step != 0 {
+        Err(UnwinnableReason::NoIntegerSolution)
+    } else if distance * step < 0 {
+        Err(UnwinnableReason::NeedsNegativePresses)
+    } else {
+        Ok(distance / step)
+    }
+}
+
+/// Finds the cheapest way to win the prize, or the reason why it cannot be won.
+fn compute_lowest_price(
+    button_a: &Button,
+    button_b: &Button,
+    target: &Target,
+    press_limit: Option<i64>,
+) -> MachineOutcome {
     let determinant = button_a.x * button_b.y - button_a.y * button_b.x;
 
     let numerator_a = button_b.y * target.x - button_b.x * target.y;
     let numerator_b = -button_a.y * target.x + button_a.x * target.y;
 
     if determinant != 0 {
-        if (numerator_a % determinant != 0)
-            || (numerator_b % determinant != 0)
-            || (numerator_a.signum() * determinant.signum() < 0)
+        if (numerator_a % determinant != 0) || (numerator_b % determinant != 0) {
+            MachineOutcome::Unwinnable(UnwinnableReason::NoIntegerSolution)
+        } else if (numerator_a.signum() * determinant.signum() < 0)
             || (numerator_b.signum() * determinant.signum() < 0)
         {
-            // no solution in the natural numbers
-            0
+            MachineOutcome::Unwinnable(UnwinnableReason::NeedsNegativePresses)
         } else {
             let n_a = numerator_a / determinant;
             let n_b = numerator_b / determinant;
-            button_a.cost * n_a + button_b.cost * n_b
+            outcome_for_presses(button_a, button_b, n_a, n_b, press_limit)
         }
     } else {
         // determinant == 0 --> no solution or infinite solutions
         if (numerator_a != 0) || (numerator_b != 0) {
-            // no solution
-            0
-        } else if (button_b.x == 0) && (button_b.y == 0) {
-            if button_a.x != 0 {
-                // solution is n_b = 0, n_a = target.x / button_a.x
-                if (target.x % button_a.x != 0) || (target.x * button_a.x < 0) {
-                    // no solution in the natural numbers
-                    return 0;
-                } else {
-                    return button_a.cost * target.x / button_a.x;
-                }
-            } else if button_a.y != 0 {
-                // solution is n_b = 0, n_a = target.y / button_a.y
-                if (target.y % button_a.y != 0) || (target.y * button_a.y < 0) {
-                    // no solution in the natural numbers
-                    return 0;
-                } else {
-                    return button_a.cost * target.y / button_a.y;
+            MachineOutcome::Unwinnable(UnwinnableReason::NoIntegerSolution)
+        } else if (button_a.x == 0 && button_a.y == 0) || (button_b.x == 0 && button_b.y == 0) {
+            // only one of the buttons moves the claw
+            let a_is_idle = button_a.x == 0 && button_a.y == 0;
+            let moving_button = if a_is_idle { button_b } else { button_a };
+            match solve_single_button(moving_button, target) {
+                Err(reason) => MachineOutcome::Unwinnable(reason),
+                Ok(num_presses) if a_is_idle => {
+                    outcome_for_presses(button_a, button_b, 0, num_presses, press_limit)
                 }
-            } else {
-                // everything is 0, solution is n_a = 0, n_b = 0
-                return 0;
-            }
-        } else if (button_a.x == 0) && (button_a.y == 0) {
-            if button_b.x != 0 {
-                // solution is n_a = 0, n_b = target.x / button_b.x
-                if (target.x % button_b.x != 0) || (target.x * button_b.x < 0) {
-                    // no solution in the natural numbers
-                    return 0;
-                } else {
-                    return button_b.cost * target.x / button_b.x;
+                Ok(num_presses) => {
+                    outcome_for_presses(button_a, button_b, num_presses, 0, press_limit)
                 }
-            } else if button_b.y != 0 {
-                // solution is n_a = 0, n_b = target.y / button_b.y
-                if (target.y % button_b.y != 0) || (target.y * button_b.y < 0) {
-                    // no solution in the natural numbers
-                    return 0;
-                } else {
-                    return button_b.cost * target.y / button_b.y;
-                }
-            } else {
-                // everything is 0, solution is n_a = 0, n_b = 0
-                return 0;
             }
-        } else if button_a.x != 0 {
-            let (gcd, u, v) = extended_gcd(button_a.x, button_b.x);
-            if target.x % gcd != 0 {
-                // no integer solutions, so no solutions
-                return 0;
-            }
-            let particular_n_a = u * target.x / gcd;
-            let particular_n_b = v * target.x / gcd;
-            let reduced_a_x = button_a.x / gcd;
-            let reduced_b_x = button_b.x / gcd;
-
-            let t_min = -particular_n_a / reduced_b_x;
-            let t_max = particular_n_b / reduced_a_x;
-            if t_min > t_max {
-                // no nonnegative solutions
-                return 0;
-            }
-            let mut min_cost = i64::MAX;
-            for t in t_min..=t_max {
-                let n_a = particular_n_a + t * reduced_b_x;
-                let n_b = particular_n_b - t * reduced_a_x;
-                let cost = button_a.cost * n_a + button_b.cost * n_b;
-                if (n_a >= 0) && (n_b >= 0) && (cost < min_cost) {
-                    min_cost = cost;
-                }
-            }
-            return min_cost;
         } else {
-            // button_a.y != 0 must be the case
-            let (gcd, u, v) = extended_gcd(button_a.y, button_b.y);
-            if target.y % gcd != 0 {
+            // both buttons move the claw along the same line, so solve along one axis
+            let (a_step, b_step, distance) = if button_a.x != 0 {
+                (button_a.x, button_b.x, target.x)
+            } else {
+                // button_a.y != 0 must be the case
+                (button_a.y, button_b.y, target.y)
+            };
+            let (gcd, u, v) = extended_gcd(a_step, b_step);
+            if distance % gcd != 0 {
                 // no integer solutions, so no solutions
-                return 0;
+                return MachineOutcome::Unwinnable(UnwinnableReason::NoIntegerSolution);
             }
-            let particular_n_a = u * target.y / gcd;
-            let particular_n_b = v * target.y / gcd;
-            let reduced_a_y = button_a.y / gcd;
-            let reduced_b_y = button_b.y / gcd;
+            let particular_n_a = u * distance / gcd;
+            let particular_n_b = v * distance / gcd;
+            let reduced_a_step = a_step / gcd;
+            let reduced_b_step = b_step / gcd;
 
-            let t_min = -particular_n_a / reduced_b_y;
-            let t_max = particular_n_b / reduced_a_y;
+            let t_min = -particular_n_a / reduced_b_step;
+            let t_max = particular_n_b / reduced_a_step;
             if t_min > t_max {
                 // no nonnegative solutions
-                return 0;
+                return MachineOutcome::Unwinnable(UnwinnableReason::NeedsNegativePresses);
             }
-            let mut min_cost = i64::MAX;
+            let mut best_outcome =
+                MachineOutcome::Unwinnable(UnwinnableReason::NeedsNegativePresses);
             for t in t_min..=t_max {
-                let n_a = particular_n_a + t * reduced_b_y;
-                let n_b = particular_n_b - t * reduced_a_y;
-                let cost = button_a.cost * n_a + button_b.cost * n_b;
-                if (n_a >= 0) && (n_b >= 0) && (cost < min_cost) {
-                    min_cost = cost;
+                let n_a = particular_n_a + t * reduced_b_step;
+                let n_b = particular_n_b - t * reduced_a_step;
+                if (n_a < 0) || (n_b < 0) {
+                    continue;
                 }
+                let outcome = outcome_for_presses(button_a, button_b, n_a, n_b, press_limit);
+                // keep the cheapest win; any solution explains a failure better than none
+                best_outcome = match (best_outcome, outcome) {
+                    (MachineOutcome::Won { .. }, MachineOutcome::Won { .. })
+                        if outcome.cost() >= best_outcome.cost() =>
+                    {
+                        best_outcome
+                    }
+                    (MachineOutcome::Won { .. }, MachineOutcome::Unwinnable(_)) => best_outcome,
+                    _ => outcome,
+                };
             }
-            return min_cost;
+            best_outcome
         }
     }
 }
 
+impl ClawMachine {
+    pub fn play(&self, press_limit: Option<i64>) -> MachineOutcome {
+        compute_lowest_price(&self.button_a, &self.button_b, &self.target, press_limit)
+    }
+}
+
 fn parse_button_line(line: &str) -> (i64, i64) {
     let x_start = line.find("X").unwrap() + 1;
     let x_end = line.find(",").unwrap();
@@ -199,20 +286,7 @@ fn parse_target_line(line: &str) -> (i64, i64) {
     (x, y)
 }
 
-pub fn run() -> Result<(), Error> {
-    let _ = task1();
-    let _ = task2();
-
-    println!("Completed solutions for Day 13!");
-
-    Ok(())
-}
-
-fn task1() -> Result<(), Error> {
-    println!("Computing solution for task 1 of Day 13...");
-
-    let input_data = fs::read_to_string("input_data/day13_input.txt")?;
-
+fn parse_claw_machines(input_data: &str, rules: ClawMachineRules) -> Vec<ClawMachine> {
     let mut claw_machine_configs: Vec<Vec<&str>> = Vec::new();
     let mut current_claw_machine_config: Vec<&str> = Vec::new();
 
@@ -228,7 +302,7 @@ fn task1() -> Result<(), Error> {
         claw_machine_configs.push(current_claw_machine_config);
     }
 
-    let mut tokens_needed = 0;
+    let mut claw_machines = Vec::new();
     for claw_machine_config in claw_machine_configs {
         let mut button_a = Button::new();
         let mut button_b = Button::new();
@@ -237,19 +311,78 @@ fn task1() -> Result<(), Error> {
         for line in claw_machine_config {
             if line.starts_with("Button A:") {
                 let (x, y) = parse_button_line(line);
-                button_a = Button { x, y, cost: 3 };
+                button_a = Button {
+                    x,
+                    y,
+                    cost: rules.cost_a,
+                };
             } else if line.starts_with("Button B:") {
                 let (x, y) = parse_button_line(line);
-                button_b = Button { x, y, cost: 1 };
+                button_b = Button {
+                    x,
+                    y,
+                    cost: rules.cost_b,
+                };
             } else if line.starts_with("Prize:") {
                 let (x, y) = parse_target_line(line);
-                target = Target { x, y };
+                target = Target {
+                    x: x + rules.prize_offset,
+                    y: y + rules.prize_offset,
+                };
             }
         }
 
-        tokens_needed += compute_lowest_price(button_a, button_b, target);
+        claw_machines.push(ClawMachine {
+            button_a,
+            button_b,
+            target,
+        });
     }
 
+    claw_machines
+}
+
+/// Plays every machine and writes an explanation of each outcome to `explanation_path`.
+fn play_all_machines(
+    input_data: &str,
+    rules: ClawMachineRules,
+    explanation_path: &str,
+) -> Result<i64, Error> {
+    let mut explanation_file = fs::File::create(explanation_path)?;
+
+    let mut tokens_needed = 0;
+    for (machine_idx, claw_machine) in parse_claw_machines(input_data, rules).iter().enumerate() {
+        let outcome = claw_machine.play(rules.press_limit);
+        writeln!(explanation_file, "Machine {}: {}", machine_idx + 1, outcome)?;
+        tokens_needed += outcome.cost();
+    }
+
+    Ok(tokens_needed)
+}
+
+pub fn run() -> Result<(), Error> {
+    let _ = task1();
+    let _ = task2();
+
+    println!("Completed solutions for Day 13!");
+
+    Ok(())
+}
+
+fn task1() -> Result<(), Error> {
+    println!("Computing solution for task 1 of Day 13...");
+
+    let input_data = fs::read_to_string("input_data/day13_input.txt")?;
+
+    let rules = ClawMachineRules {
+        cost_a: 3,
+        cost_b: 1,
+        press_limit: Some(100),
+        prize_offset: 0,
+    };
+    let tokens_needed =
+        play_all_machines(&input_data, rules, "solutions/day13_task1_machines.txt")?;
+
     let mut solution_file = fs::File::create("solutions/day13_solution.txt")?;
     writeln!(solution_file, "Solution for Task 1 of Day 13:")?;
     writeln!(
@@ -266,45 +399,14 @@ fn task2() -> Result<(), Error> {
 
     let input_data = fs::read_to_string("input_data/day13_input.txt")?;
 
-    let mut claw_machine_configs: Vec<Vec<&str>> = Vec::new();
-    let mut current_claw_machine_config: Vec<&str> = Vec::new();
-
-    for line in input_data.lines() {
-        if line.trim().is_empty() && (!current_claw_machine_config.is_empty()) {
-            claw_machine_configs.push(current_claw_machine_config.clone());
-            current_claw_machine_config.clear();
-        } else {
-            current_claw_machine_config.push(line);
-        }
-    }
-    if !current_claw_machine_config.is_empty() {
-        claw_machine_configs.push(current_claw_machine_config);
-    }
-
-    let mut tokens_needed = 0;
-    for claw_machine_config in claw_machine_configs {
-        let mut button_a = Button::new();
-        let mut button_b = Button::new();
-        let mut target = Target::new();
-
-        for line in claw_machine_config {
-            if line.starts_with("Button A:") {
-                let (x, y) = parse_button_line(line);
-                button_a = Button { x, y, cost: 3 };
-            } else if line.starts_with("Button B:") {
-                let (x, y) = parse_button_line(line);
-                button_b = Button { x, y, cost: 1 };
-            } else if line.starts_with("Prize:") {
-                let (x, y) = parse_target_line(line);
-                target = Target {
-                    x: x + 10000000000000,
-                    y: y + 10000000000000,
-                };
-            }
-        }
-
-        tokens_needed += compute_lowest_price(button_a, button_b, target);
-    }
+    let rules = ClawMachineRules {
+        cost_a: 3,
+        cost_b: 1,
+        press_limit: None,
+        prize_offset: 10000000000000,
+    };
+    let tokens_needed =
+        play_all_machines(&input_data, rules, "solutions/day13_task2_machines.txt")?;
 
     let mut solution_file = fs::OpenOptions::new()
         .append(true)
@@ -320,3 +422,31 @@ fn task2() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buttons_that_do_not_move() {
+        let button_a = Button {
+            x: 0,
+            y: 0,
+            cost: 3,
+        };
+        let button_b = Button {
+            x: 0,
+            y: 0,
+            cost: 1,
+        };
+
+        assert_eq!(
+            compute_lowest_price(&button_a, &button_b, &Target { x: 37, y: 34 }, None),
+            MachineOutcome::Unwinnable(UnwinnableReason::NoIntegerSolution)
+        );
+        assert_eq!(
+            compute_lowest_price(&button_a, &button_b, &Target { x: 0, y: 0 }, None).cost(),
+            0
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use std::fs;
-use std::io::Error;
 use std::io::Write;
-use std::io::{stdin, stdout};
+use std::io::{Error, ErrorKind};
+use std::path::Path;
 
 #[derive(Clone, Copy)]
 struct Robot {
@@ -26,6 +26,16 @@ impl Robot {
             vel_y: vel_y.parse().ok().unwrap(),
         }
     }
+
+    /// The position (x, y) after `time_elapsed` seconds, without moving the robot.
+    fn position_at(&self, time_elapsed: i64, map_width: u32, map_height: u32) -> (u32, u32) {
+        let pos_x =
+            (self.pos_x as i64 + self.vel_x as i64 * time_elapsed).rem_euclid(map_width as i64);
+        let pos_y =
+            (self.pos_y as i64 + self.vel_y as i64 * time_elapsed).rem_euclid(map_height as i64);
+
+        (pos_x as u32, pos_y as u32)
+    }
 }
 
 enum Quadrant {
@@ -36,43 +46,249 @@ enum Quadrant {
     Middle,
 }
 
-fn simulate_robot_movement(robot: &mut Robot, time_elapsed: i64, map_height: u32, map_width: u32) {
-    let delta_x =
-        ((robot.vel_x as i64 * time_elapsed) % map_width as i64 + map_width as i64) as u32;
-    let delta_y =
-        ((robot.vel_y as i64 * time_elapsed) % map_height as i64 + map_height as i64) as u32;
+/// How many robots are in each quadrant, with the robots on the middle lines counted separately.
+#[derive(Clone, Copy, Default)]
+struct QuadrantHistogram {
+    top_right: u32,
+    top_left: u32,
+    bottom_left: u32,
+    bottom_right: u32,
+    middle: u32,
+}
+impl QuadrantHistogram {
+    fn add(&mut self, quadrant: Quadrant) {
+        match quadrant {
+            Quadrant::TopRight => self.top_right += 1,
+            Quadrant::TopLeft => self.top_left += 1,
+            Quadrant::BottomLeft => self.bottom_left += 1,
+            Quadrant::BottomRight => self.bottom_right += 1,
+            Quadrant::Middle => self.middle += 1,
+        }
+    }
 
-    robot.pos_x = (robot.pos_x + delta_x) % map_width;
-    robot.pos_y = (robot.pos_y + delta_y) % map_height;
+    fn safety_factor(&self) -> u64 {
+        self.top_right as u64
+            * self.top_left as u64
+            * self.bottom_left as u64
+            * self.bottom_right as u64
+    }
+}
+
+fn simulate_robot_movement(robot: &mut Robot, time_elapsed: i64, map_height: u32, map_width: u32) {
+    (robot.pos_x, robot.pos_y) = robot.position_at(time_elapsed, map_width, map_height);
 }
 
-fn get_robot_quadrant(robot: Robot, map_height: u32, map_width: u32) -> Quadrant {
+fn get_robot_quadrant((pos_x, pos_y): (u32, u32), map_height: u32, map_width: u32) -> Quadrant {
     let lowest_top = map_height / 2 - 1;
-    let highest_bottom = (map_height + 1) / 2;
+    let highest_bottom = map_height.div_ceil(2);
 
     let rightest_left = map_width / 2 - 1;
-    let leftest_right = (map_width + 1) / 2;
+    let leftest_right = map_width.div_ceil(2);
 
-    if (robot.pos_x >= leftest_right) && (robot.pos_y <= lowest_top) {
+    if (pos_x >= leftest_right) && (pos_y <= lowest_top) {
         Quadrant::TopRight
-    } else if (robot.pos_x <= rightest_left) && (robot.pos_y <= lowest_top) {
+    } else if (pos_x <= rightest_left) && (pos_y <= lowest_top) {
         Quadrant::TopLeft
-    } else if (robot.pos_x <= rightest_left) && (robot.pos_y >= highest_bottom) {
+    } else if (pos_x <= rightest_left) && (pos_y >= highest_bottom) {
         Quadrant::BottomLeft
-    } else if (robot.pos_x >= leftest_right) && (robot.pos_y >= highest_bottom) {
+    } else if (pos_x >= leftest_right) && (pos_y >= highest_bottom) {
         Quadrant::BottomRight
     } else {
         Quadrant::Middle
     }
 }
 
-fn print_state(
-    robots: &Vec<Robot>,
+fn quadrant_histogram(
+    robots: &[Robot],
+    time_elapsed: i64,
+    map_height: u32,
+    map_width: u32,
+) -> QuadrantHistogram {
+    let mut histogram = QuadrantHistogram::default();
+    for robot in robots {
+        let position = robot.position_at(time_elapsed, map_width, map_height);
+        histogram.add(get_robot_quadrant(position, map_height, map_width));
+    }
+    histogram
+}
+
+/// The safety factor at every timestep in `timesteps`.
+fn safety_factors(
+    robots: &[Robot],
+    timesteps: impl Iterator<Item = i64>,
+    map_height: u32,
+    map_width: u32,
+) -> Vec<(i64, u64)> {
+    timesteps
+        .map(|time_elapsed| {
+            let histogram = quadrant_histogram(robots, time_elapsed, map_height, map_width);
+            (time_elapsed, histogram.safety_factor())
+        })
+        .collect()
+}
+
+/// Writes the safety factor over a full period of the robot movement, after which every robot is
+/// back at its starting position.
+fn write_safety_factor_report() -> Result<(), Error> {
+    println!("Writing safety factor report for Day 14...");
+
+    let input_data = fs::read_to_string("input_data/day14_input.txt")?;
+    let map_height = 103;
+    let map_width = 101;
+    let robots: Vec<Robot> = input_data.lines().map(Robot::from_config).collect();
+
+    let period = map_height as i64 * map_width as i64;
+    let safety_factors = safety_factors(&robots, 0..period, map_height, map_width);
+
+    let mut solution_file = fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open("solutions/day14_solution.txt")?;
+    if let Some((safest_time, lowest_safety_factor)) = safety_factors
+        .iter()
+        .min_by_key(|(_, safety_factor)| *safety_factor)
+    {
+        writeln!(
+            solution_file,
+            "Over a full period of {} seconds, the safety factor is lowest ({}) after {} seconds.",
+            period, lowest_safety_factor, safest_time
+        )?;
+    }
+
+    // the full series is generated output, so it goes to scratch/ with the frame exports
+    fs::create_dir_all("scratch")?;
+    let mut csv_file = fs::File::create("scratch/day14_safety_factors.csv")?;
+    writeln!(csv_file, "timestep,safety_factor")?;
+    for (time_elapsed, safety_factor) in safety_factors.iter() {
+        writeln!(csv_file, "{},{}", time_elapsed, safety_factor)?;
+    }
+
+    Ok(())
+}
+
+/// The number of robots on every tile after `time_elapsed` seconds, indexed [y][x].
+fn robot_counts(
+    robots: &[Robot],
+    time_elapsed: i64,
+    map_height: u32,
+    map_width: u32,
+) -> Vec<Vec<u32>> {
+    let mut counts = vec![vec![0; map_width as usize]; map_height as usize];
+    for robot in robots {
+        let (pos_x, pos_y) = robot.position_at(time_elapsed, map_width, map_height);
+        counts[pos_y as usize][pos_x as usize] += 1;
+    }
+    counts
+}
+
+#[derive(Clone, Copy)]
+enum FrameFormat {
+    // one black and white image per timestep, black where there is at least one robot
+    Pbm,
+    // one greyscale image per timestep, brighter the more robots share a tile
+    Pgm,
+    // all timesteps in a single text file, each frame preceded by a header with its timestep
+    MultiFrameText,
+}
+
+fn write_pbm(file: &mut fs::File, counts: &[Vec<u32>]) -> Result<(), Error> {
+    let height = counts.len();
+    let width = counts.first().map_or(0, |row| row.len());
+    write!(file, "P4\n{} {}\n", width, height)?;
+
+    // every row is packed into bytes, most significant bit first, with 1 meaning black
+    for row in counts {
+        let mut packed_row = vec![0u8; width.div_ceil(8)];
+        for (x, &count) in row.iter().enumerate() {
+            if count > 0 {
+                packed_row[x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        file.write_all(&packed_row)?;
+    }
+
+    Ok(())
+}
+
+fn write_pgm(file: &mut fs::File, counts: &[Vec<u32>]) -> Result<(), Error> {
+    let height = counts.len();
+    let width = counts.first().map_or(0, |row| row.len());
+    let max_count = counts
+        .iter()
+        .flatten()
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .clamp(1, 255);
+    write!(file, "P5\n{} {}\n{}\n", width, height, max_count)?;
+
+    for row in counts {
+        let grey_values: Vec<u8> = row
+            .iter()
+            .map(|&count| count.min(max_count) as u8)
+            .collect();
+        file.write_all(&grey_values)?;
+    }
+
+    Ok(())
+}
+
+/// Exports the robot positions at every timestep in `timesteps`. The image formats write one
+/// file per timestep into the directory `output_path`, the text format writes a single file.
+fn export_frames(
+    robots: &[Robot],
+    timesteps: impl Iterator<Item = i64>,
     map_height: u32,
     map_width: u32,
-    filepath: &str,
-    prefix: &str,
+    format: FrameFormat,
+    output_path: &str,
 ) -> Result<(), Error> {
+    match format {
+        FrameFormat::Pbm | FrameFormat::Pgm => {
+            fs::create_dir_all(output_path)?;
+            for time_elapsed in timesteps {
+                let counts = robot_counts(robots, time_elapsed, map_height, map_width);
+                let extension = if let FrameFormat::Pbm = format {
+                    "pbm"
+                } else {
+                    "pgm"
+                };
+                let mut frame_file = fs::File::create(format!(
+                    "{}/frame_{:05}.{}",
+                    output_path, time_elapsed, extension
+                ))?;
+                if let FrameFormat::Pbm = format {
+                    write_pbm(&mut frame_file, &counts)?;
+                } else {
+                    write_pgm(&mut frame_file, &counts)?;
+                }
+            }
+        }
+        FrameFormat::MultiFrameText => {
+            if let Some(parent_dir) = Path::new(output_path).parent() {
+                fs::create_dir_all(parent_dir)?;
+            }
+            let mut frames_file = fs::File::create(output_path)?;
+            for time_elapsed in timesteps {
+                let mut moved_robots = robots.to_vec();
+                for robot in moved_robots.iter_mut() {
+                    simulate_robot_movement(robot, time_elapsed, map_height, map_width);
+                }
+                writeln!(frames_file, "Map State after {}s", time_elapsed)?;
+                writeln!(
+                    frames_file,
+                    "{}",
+                    render_state(&moved_robots, map_height, map_width)
+                )?;
+                writeln!(frames_file)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn render_state(robots: &[Robot], map_height: u32, map_width: u32) -> String {
     let mut map_state = vec![vec!['.'; map_width as usize]; map_height as usize];
 
     for robot in robots {
@@ -82,8 +298,10 @@ fn print_state(
         .iter()
         .map(|chars| chars.iter().collect::<String>())
         .collect();
-    let image = lines.join("\n");
+    lines.join("\n")
+}
 
+fn print_state(image: &str, filepath: &str, prefix: &str) -> Result<(), Error> {
     let mut print_file = fs::File::create(filepath)?;
     writeln!(print_file, "{}", prefix)?;
     writeln!(print_file, "{}", image)?;
@@ -91,9 +309,70 @@ fn print_state(
     Ok(())
 }
 
+fn variance(values: impl Iterator<Item = u32>) -> f64 {
+    let values: Vec<f64> = values.map(|value| value as f64).collect();
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / values.len() as f64
+}
+
+/// The smallest non-negative t with t = remainder_a (mod modulus_a) and t = remainder_b
+/// (mod modulus_b), if there is one.
+fn chinese_remainder(
+    remainder_a: i64,
+    modulus_a: i64,
+    remainder_b: i64,
+    modulus_b: i64,
+) -> Option<i64> {
+    (0..modulus_b)
+        .map(|k| remainder_a + k * modulus_a)
+        .find(|t| t % modulus_b == remainder_b)
+}
+
+/// Finds the timestep at which the robots form a picture, together with the rendered frame.
+///
+/// The x coordinates repeat every `map_width` steps and the y coordinates every `map_height`
+/// steps. Within each period the picture shows up as the step where the robots are most tightly
+/// clustered, i.e. where the variance of their positions is lowest, and the two steps are then
+/// combined into a single timestep.
+fn detect_christmas_tree(
+    robots: &[Robot],
+    map_height: u32,
+    map_width: u32,
+) -> Option<(i64, String)> {
+    let variance_at = |time_elapsed: i64, coordinate: fn(&Robot) -> u32| {
+        let mut moved_robots = robots.to_vec();
+        for robot in moved_robots.iter_mut() {
+            simulate_robot_movement(robot, time_elapsed, map_height, map_width);
+        }
+        variance(moved_robots.iter().map(coordinate))
+    };
+    let most_clustered_step = |period: u32, coordinate: fn(&Robot) -> u32| {
+        (0..period as i64)
+            .map(|time_elapsed| (time_elapsed, variance_at(time_elapsed, coordinate)))
+            .min_by(|(_, variance_a), (_, variance_b)| variance_a.total_cmp(variance_b))
+            .map_or(0, |(time_elapsed, _)| time_elapsed)
+    };
+
+    let x_step = most_clustered_step(map_width, |robot| robot.pos_x);
+    let y_step = most_clustered_step(map_height, |robot| robot.pos_y);
+    let timestep = chinese_remainder(x_step, map_width as i64, y_step, map_height as i64)?;
+
+    let mut tree_robots = robots.to_vec();
+    for robot in tree_robots.iter_mut() {
+        simulate_robot_movement(robot, timestep, map_height, map_width);
+    }
+
+    Some((timestep, render_state(&tree_robots, map_height, map_width)))
+}
+
 pub fn run() -> Result<(), Error> {
     let _ = task1();
     let _ = task2();
+    let _ = write_safety_factor_report();
 
     println!("Completed solutions for Day 14!");
 
@@ -105,32 +384,13 @@ fn task1() -> Result<(), Error> {
 
     let input_data = fs::read_to_string("input_data/day14_input.txt")?;
 
-    let mut num_topright_quadrant: u32 = 0;
-    let mut num_topleft_quadrant: u32 = 0;
-    let mut num_bottomleft_quadrant: u32 = 0;
-    let mut num_bottomright_quadrant: u32 = 0;
-
     let map_height = 103;
     let map_width = 101;
     let simulation_time = 100;
 
-    for robot_config in input_data.lines() {
-        let mut robot = Robot::from_config(robot_config);
-        simulate_robot_movement(&mut robot, simulation_time, map_height, map_width);
-
-        match get_robot_quadrant(robot, map_height, map_width) {
-            Quadrant::TopRight => num_topright_quadrant += 1,
-            Quadrant::TopLeft => num_topleft_quadrant += 1,
-            Quadrant::BottomLeft => num_bottomleft_quadrant += 1,
-            Quadrant::BottomRight => num_bottomright_quadrant += 1,
-            Quadrant::Middle => {}
-        }
-    }
-
-    let safety_factor = num_topright_quadrant
-        * num_topleft_quadrant
-        * num_bottomleft_quadrant
-        * num_bottomright_quadrant;
+    let robots: Vec<Robot> = input_data.lines().map(Robot::from_config).collect();
+    let safety_factor =
+        quadrant_histogram(&robots, simulation_time, map_height, map_width).safety_factor();
 
     let mut solution_file = fs::File::create("solutions/day14_solution.txt")?;
     writeln!(solution_file, "Solution for Task 1 of Day 14:")?;
@@ -155,42 +415,18 @@ fn task2() -> Result<(), Error> {
         robots.push(Robot::from_config(robot_config));
     }
 
-    let mut user_command = String::new();
-    let mut time_elapsed = 0;
-    let delta_t = 1;
-
-    for robot in robots.iter_mut() {
-        simulate_robot_movement(robot, time_elapsed, map_height, map_width);
-    }
-    loop {
-        let prefix = format!("Map State after {}s", time_elapsed);
-        print_state(
-            &robots,
-            map_height,
-            map_width,
-            "scratch/day14_task2_visualisation.txt",
-            &prefix,
-        )?;
-
-        println!("If you want to quit, please enter 'q'.");
-        stdout().flush().unwrap();
+    let (tree_timestep, tree_frame) = detect_christmas_tree(&robots, map_height, map_width)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "No Christmas tree found"))?;
 
-        stdin()
-            .read_line(&mut user_command)
-            .expect("Failed to read input.");
-
-        if user_command.trim() == "q" {
-            break;
-        }
-        for robot in robots.iter_mut() {
-            simulate_robot_movement(robot, delta_t, map_height, map_width);
-        }
-        time_elapsed += delta_t;
-    }
+    print_state(
+        &tree_frame,
+        &format!(
+            "solutions/day14_task2_visualisation_timestep{}.txt",
+            tree_timestep
+        ),
+        &format!("Map State after {}s", tree_timestep),
+    )?;
 
-    // There are some horizontal patterns at t = 16 + 103 * n, n=0,1,2,... and some vertical patterns at t = 71 + 101 * m, m=0,1,2,...
-    // This suggests (which was easily confirmed) that at the first timestep where both those patterns coincide, a stronger pattern (of the Christmas tree) appears.
-    // We have 16 + 103n = 71 + 101m => 55 = 103n - 101m => 55 = 2n - 101d for m = n+d => LHS first becomes odd for d = 1, and n = 78, so at t = 8050. This is indeed the correct timestep.
     let mut solution_file = fs::OpenOptions::new()
         .append(true)
         .create(true)
@@ -199,8 +435,27 @@ fn task2() -> Result<(), Error> {
     writeln!(solution_file, "Solution for Task 2 of Day 14:")?;
     writeln!(
         solution_file,
-        "The robots look like a Christmas Tree after 8050 seconds."
+        "The robots look like a Christmas Tree after {} seconds.",
+        tree_timestep
     )?;
 
+    // the frames around the tree, to scrub through in an image viewer; written after the solution
+    // so a failing export doesn't lose it
+    let frame_window = (tree_timestep - 10).max(0)..=(tree_timestep + 10);
+    for (format, output_path) in [
+        (FrameFormat::Pbm, "scratch/day14_frames_pbm"),
+        (FrameFormat::Pgm, "scratch/day14_frames_pgm"),
+        (FrameFormat::MultiFrameText, "scratch/day14_frames.txt"),
+    ] {
+        export_frames(
+            &robots,
+            frame_window.clone(),
+            map_height,
+            map_width,
+            format,
+            output_path,
+        )?;
+    }
+
     Ok(())
 }
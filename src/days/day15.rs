@@ -1,8 +1,8 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
 use std::hash::Hash;
-use std::io::Error;
 use std::io::Write;
+use std::io::{Error, ErrorKind};
 
 #[derive(Clone, Copy, PartialEq)]
 enum Movement {
@@ -11,6 +11,17 @@ enum Movement {
     Left,
     Right,
 }
+impl Movement {
+    /// The (row, col) step of the movement.
+    fn delta(self) -> (i32, i32) {
+        match self {
+            Movement::Up => (-1, 0),
+            Movement::Down => (1, 0),
+            Movement::Left => (0, -1),
+            Movement::Right => (0, 1),
+        }
+    }
+}
 
 #[derive(Eq, Hash, PartialEq, Clone, Copy)]
 struct Location {
@@ -22,313 +33,374 @@ impl Location {
         Location { row: 0, col: 0 }
     }
 }
-struct Map {
+/// A warehouse with a robot pushing boxes around. Every tile of the input map becomes
+/// `tile_width` tiles wide, so boxes are `tile_width` times as wide as in the input. Boxes can be
+/// written as `O` or, for boxes that are already wide, as `[]` or `[==]`.
+struct Warehouse {
     robot_pos: Location,
     wall_pos: HashSet<Location>,
-    box_pos: HashSet<Location>,
+    // the leftmost tile of every box, indexed by box ID
+    box_pos: Vec<Location>,
+    box_width: i32,
+    // the box ID for every tile covered by a box
+    box_tiles: HashMap<Location, usize>,
 }
-impl Map {
-    fn from_str(input: &str) -> Map {
+impl Warehouse {
+    fn from_str(input: &str, tile_width: i32) -> Result<Warehouse, Error> {
         let mut robot_pos = Location::new();
         let mut wall_pos = HashSet::new();
-        let mut box_pos = HashSet::new();
+        let mut box_pos = Vec::new();
+        let mut box_width: Option<i32> = None;
         for (row, line) in input.lines().enumerate() {
+            let mut box_start_col: Option<usize> = None;
             for (col, char) in line.char_indices() {
-                match char {
-                    '#' => wall_pos.insert(Location {
-                        row: row as i32,
-                        col: col as i32,
-                    }),
-                    'O' => box_pos.insert(Location {
-                        row: row as i32,
-                        col: col as i32,
-                    }),
+                let tile_start = Location {
+                    row: row as i32,
+                    col: col as i32 * tile_width,
+                };
+                let finished_box_width = match char {
+                    '#' => {
+                        for offset in 0..tile_width {
+                            wall_pos.insert(Location {
+                                row: tile_start.row,
+                                col: tile_start.col + offset,
+                            });
+                        }
+                        None
+                    }
+                    'O' => {
+                        box_pos.push(tile_start);
+                        Some(tile_width)
+                    }
+                    '[' => {
+                        if box_start_col.is_some() {
+                            return Err(Error::new(
+                                ErrorKind::InvalidData,
+                                format!("Box opened inside another box in row {}", row),
+                            ));
+                        }
+                        box_pos.push(tile_start);
+                        box_start_col = Some(col);
+                        None
+                    }
+                    ']' => {
+                        let start_col = box_start_col.take().ok_or_else(|| {
+                            Error::new(
+                                ErrorKind::InvalidData,
+                                format!("Box closed without being opened in row {}", row),
+                            )
+                        })?;
+                        Some((col - start_col + 1) as i32 * tile_width)
+                    }
                     '@' => {
-                        robot_pos = Location {
-                            row: row as i32,
-                            col: col as i32,
-                        };
-                        true
+                        robot_pos = tile_start;
+                        None
                     }
-                    _ => true,
+                    _ => None,
                 };
+                if let Some(finished_box_width) = finished_box_width {
+                    let box_width = *box_width.get_or_insert(finished_box_width);
+                    if box_width != finished_box_width {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            "All boxes must be equally wide",
+                        ));
+                    }
+                }
+            }
+            if box_start_col.is_some() {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Box opened but not closed in row {}", row),
+                ));
             }
         }
 
-        Map {
+        let mut warehouse = Warehouse {
             robot_pos,
             wall_pos,
             box_pos,
+            box_width: box_width.unwrap_or(tile_width),
+            box_tiles: HashMap::new(),
+        };
+        for box_id in 0..warehouse.box_pos.len() {
+            warehouse.place_box(box_id);
         }
+        Ok(warehouse)
     }
 
-    fn sum_box_gps_coords(&self) -> i32 {
-        let mut sum_gps_coords = 0;
-        for box_location in self.box_pos.iter() {
-            sum_gps_coords += box_location.row.abs() * 100 + box_location.col.abs();
+    /// Turns a warehouse into one where everything except the robot is twice as wide.
+    fn widen(&self) -> Warehouse {
+        let widened = |location: &Location| Location {
+            row: location.row,
+            col: 2 * location.col,
+        };
+
+        let mut wall_pos = HashSet::new();
+        for wall in self.wall_pos.iter() {
+            wall_pos.insert(widened(wall));
+            wall_pos.insert(Location {
+                row: wall.row,
+                col: 2 * wall.col + 1,
+            });
         }
-        sum_gps_coords
+
+        let mut wide_warehouse = Warehouse {
+            robot_pos: widened(&self.robot_pos),
+            wall_pos,
+            box_pos: self.box_pos.iter().map(widened).collect(),
+            box_width: 2 * self.box_width,
+            box_tiles: HashMap::new(),
+        };
+        for box_id in 0..wide_warehouse.box_pos.len() {
+            wide_warehouse.place_box(box_id);
+        }
+        wide_warehouse
     }
 
-    fn move_robot(&mut self, movement: Movement) {
-        let mut current_pos = self.robot_pos;
-
-        let mut movement_possible = true;
-        let mut last_moved_box: Option<Location> = None;
-        loop {
-            match movement {
-                Movement::Up => current_pos.row -= 1,
-                Movement::Down => current_pos.row += 1,
-                Movement::Left => current_pos.col -= 1,
-                Movement::Right => current_pos.col += 1,
-            };
-            if self.box_pos.contains(&current_pos) {
-                last_moved_box = Some(current_pos);
-            } else if self.wall_pos.contains(&current_pos) {
-                movement_possible = false;
-                break;
-            } else {
-                break;
-            }
+    /// Checks that the robot and every box occupy only free tiles, and that the tiles recorded
+    /// for each box are exactly the ones it covers.
+    fn check_invariants(&self) -> Result<(), Error> {
+        let all_boxes: Vec<usize> = (0..self.box_pos.len()).collect();
+        self.check_invariants_for(&all_boxes)?;
+
+        // every box tile belongs to a box, so the left and right halves of boxes are paired up
+        if self.box_tiles.len() != self.box_pos.len() * self.box_width as usize {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "There are box tiles that belong to no box",
+            ));
         }
-        if movement_possible {
-            match movement {
-                Movement::Up => {
-                    self.robot_pos.row -= 1;
-                    self.box_pos.remove(&self.robot_pos);
-                    if let Some(last_moved_box_pos) = last_moved_box {
-                        self.box_pos.insert(Location {
-                            row: last_moved_box_pos.row - 1,
-                            col: last_moved_box_pos.col,
-                        });
-                    }
-                }
-                Movement::Down => {
-                    self.robot_pos.row += 1;
-                    self.box_pos.remove(&self.robot_pos);
-                    if let Some(last_moved_box_pos) = last_moved_box {
-                        self.box_pos.insert(Location {
-                            row: last_moved_box_pos.row + 1,
-                            col: last_moved_box_pos.col,
-                        });
-                    }
+
+        Ok(())
+    }
+
+    /// The invariants restricted to the robot and the given boxes, cheap enough to check after
+    /// every move.
+    fn check_invariants_for(&self, boxes: &[usize]) -> Result<(), Error> {
+        let invariant_error = |message: String| Err(Error::new(ErrorKind::InvalidData, message));
+
+        if self.wall_pos.contains(&self.robot_pos) {
+            return invariant_error("The robot is inside a wall".to_string());
+        }
+        if let Some(box_id) = self.box_tiles.get(&self.robot_pos) {
+            return invariant_error(format!("The robot is inside box {}", box_id));
+        }
+
+        for &box_id in boxes {
+            for tile in self.tiles_of_box(box_id) {
+                if self.wall_pos.contains(&tile) {
+                    return invariant_error(format!("Box {} is inside a wall", box_id));
                 }
-                Movement::Left => {
-                    self.robot_pos.col -= 1;
-                    self.box_pos.remove(&self.robot_pos);
-                    if let Some(last_moved_box_pos) = last_moved_box {
-                        self.box_pos.insert(Location {
-                            row: last_moved_box_pos.row,
-                            col: last_moved_box_pos.col - 1,
-                        });
+                match self.box_tiles.get(&tile) {
+                    Some(&tile_box_id) if tile_box_id == box_id => {}
+                    Some(&tile_box_id) => {
+                        return invariant_error(format!(
+                            "Boxes {} and {} overlap",
+                            box_id, tile_box_id
+                        ))
                     }
-                }
-                Movement::Right => {
-                    self.robot_pos.col += 1;
-                    self.box_pos.remove(&self.robot_pos);
-                    if let Some(last_moved_box_pos) = last_moved_box {
-                        self.box_pos.insert(Location {
-                            row: last_moved_box_pos.row,
-                            col: last_moved_box_pos.col + 1,
-                        });
+                    None => {
+                        return invariant_error(format!(
+                            "Part of box {} is missing from the map",
+                            box_id
+                        ))
                     }
                 }
-            };
+            }
         }
+
+        Ok(())
     }
-}
-struct WideMap {
-    robot_pos: Location,
-    wall_pos: HashSet<Location>,
-    box_pos_left: HashSet<Location>,
-    box_pos_right: HashSet<Location>,
-}
-impl WideMap {
-    fn from_str(input: &str) -> WideMap {
-        let mut robot_pos = Location::new();
-        let mut wall_pos = HashSet::new();
-        let mut box_pos_left = HashSet::new();
-        let mut box_pos_right = HashSet::new();
-        for (row, line) in input.lines().enumerate() {
-            for (col, char) in line.char_indices() {
-                match char {
-                    '#' => {
-                        wall_pos.insert(Location {
-                            row: row as i32,
-                            col: 2 * col as i32,
-                        });
-                        wall_pos.insert(Location {
-                            row: row as i32,
-                            col: (2 * col + 1) as i32,
-                        });
-                    }
-                    'O' => {
-                        box_pos_left.insert(Location {
-                            row: row as i32,
-                            col: 2 * col as i32,
-                        });
-                        box_pos_right.insert(Location {
-                            row: row as i32,
-                            col: (2 * col + 1) as i32,
-                        });
-                    }
-                    '@' => {
-                        robot_pos = Location {
-                            row: row as i32,
-                            col: 2 * col as i32,
-                        };
-                    }
-                    _ => (),
-                };
-            }
+
+    fn tiles_of_box(&self, box_id: usize) -> impl Iterator<Item = Location> {
+        let box_start = self.box_pos[box_id];
+        (0..self.box_width).map(move |offset| Location {
+            row: box_start.row,
+            col: box_start.col + offset,
+        })
+    }
+
+    fn place_box(&mut self, box_id: usize) {
+        for tile in self.tiles_of_box(box_id).collect::<Vec<_>>() {
+            self.box_tiles.insert(tile, box_id);
         }
+    }
 
-        WideMap {
-            robot_pos,
-            wall_pos,
-            box_pos_left,
-            box_pos_right,
+    fn remove_box(&mut self, box_id: usize) {
+        for tile in self.tiles_of_box(box_id).collect::<Vec<_>>() {
+            self.box_tiles.remove(&tile);
         }
     }
 
     fn sum_box_gps_coords(&self) -> i32 {
         let mut sum_gps_coords = 0;
-        for box_location in self.box_pos_left.iter() {
+        for box_location in self.box_pos.iter() {
             sum_gps_coords += box_location.row.abs() * 100 + box_location.col.abs();
         }
         sum_gps_coords
     }
 
-    fn move_robot(&mut self, movement: Movement) {
-        let mut current_pushed_pos: HashSet<Location> = HashSet::from([self.robot_pos]);
-
-        let mut movement_possible = true;
-        let mut pushed_boxes_left: HashSet<Location> = HashSet::new();
-        let mut pushed_boxes_right: HashSet<Location> = HashSet::new();
-        while let Some(mut current_pos) = current_pushed_pos.iter().next().cloned() {
-            current_pushed_pos.remove(&current_pos);
+    /// The IDs of all boxes the robot would push with this movement, found by following the push
+    /// from tile to tile; None if the push ends at a wall.
+    fn pushed_boxes(&self, movement: Movement) -> Option<Vec<usize>> {
+        let (row_delta, col_delta) = movement.delta();
 
-            match movement {
-                Movement::Up => current_pos.row -= 1,
-                Movement::Down => current_pos.row += 1,
-                Movement::Left => current_pos.col -= 1,
-                Movement::Right => current_pos.col += 1,
+        let mut pushed_boxes: Vec<usize> = Vec::new();
+        let mut is_pushed: HashSet<usize> = HashSet::new();
+        let mut tiles_to_check: VecDeque<Location> = VecDeque::from([self.robot_pos]);
+        while let Some(pushing_tile) = tiles_to_check.pop_front() {
+            let pushed_tile = Location {
+                row: pushing_tile.row + row_delta,
+                col: pushing_tile.col + col_delta,
             };
-            if self.box_pos_left.contains(&current_pos) {
-                pushed_boxes_left.insert(current_pos);
-                current_pushed_pos.insert(current_pos);
-                if (movement != Movement::Left) && (movement != Movement::Right) {
-                    let pushed_right_pos = Location {
-                        row: current_pos.row,
-                        col: current_pos.col + 1,
-                    };
-                    pushed_boxes_right.insert(pushed_right_pos);
-                    current_pushed_pos.insert(pushed_right_pos);
-                }
-            } else if self.box_pos_right.contains(&current_pos) {
-                pushed_boxes_right.insert(current_pos);
-                current_pushed_pos.insert(current_pos);
-                if (movement != Movement::Left) && (movement != Movement::Right) {
-                    let pushed_left_pos = Location {
-                        row: current_pos.row,
-                        col: current_pos.col - 1,
-                    };
-                    pushed_boxes_left.insert(pushed_left_pos);
-                    current_pushed_pos.insert(pushed_left_pos);
+            if self.wall_pos.contains(&pushed_tile) {
+                return None;
+            }
+            if let Some(&box_id) = self.box_tiles.get(&pushed_tile) {
+                if is_pushed.insert(box_id) {
+                    pushed_boxes.push(box_id);
+                    tiles_to_check.extend(self.tiles_of_box(box_id));
                 }
-            } else if self.wall_pos.contains(&current_pos) {
-                movement_possible = false;
-                break;
             }
         }
-        if movement_possible {
-            match movement {
-                Movement::Up => {
-                    self.robot_pos.row -= 1;
-                    for left_box_pos in pushed_boxes_left.iter() {
-                        self.box_pos_left.remove(left_box_pos);
-                    }
-                    for left_box_pos in pushed_boxes_left {
-                        self.box_pos_left.insert(Location {
-                            row: left_box_pos.row - 1,
-                            col: left_box_pos.col,
-                        });
-                    }
-                    for right_box_pos in pushed_boxes_right.iter() {
-                        self.box_pos_right.remove(right_box_pos);
-                    }
-                    for right_box_pos in pushed_boxes_right {
-                        self.box_pos_right.insert(Location {
-                            row: right_box_pos.row - 1,
-                            col: right_box_pos.col,
-                        });
-                    }
-                }
-                Movement::Down => {
-                    self.robot_pos.row += 1;
-                    for left_box_pos in pushed_boxes_left.iter() {
-                        self.box_pos_left.remove(left_box_pos);
-                    }
-                    for left_box_pos in pushed_boxes_left {
-                        self.box_pos_left.insert(Location {
-                            row: left_box_pos.row + 1,
-                            col: left_box_pos.col,
-                        });
-                    }
-                    for right_box_pos in pushed_boxes_right.iter() {
-                        self.box_pos_right.remove(right_box_pos);
-                    }
-                    for right_box_pos in pushed_boxes_right {
-                        self.box_pos_right.insert(Location {
-                            row: right_box_pos.row + 1,
-                            col: right_box_pos.col,
-                        });
-                    }
-                }
-                Movement::Left => {
-                    self.robot_pos.col -= 1;
-                    for left_box_pos in pushed_boxes_left.iter() {
-                        self.box_pos_left.remove(left_box_pos);
-                    }
-                    for left_box_pos in pushed_boxes_left {
-                        self.box_pos_left.insert(Location {
-                            row: left_box_pos.row,
-                            col: left_box_pos.col - 1,
-                        });
-                    }
-                    for right_box_pos in pushed_boxes_right.iter() {
-                        self.box_pos_right.remove(right_box_pos);
-                    }
-                    for right_box_pos in pushed_boxes_right {
-                        self.box_pos_right.insert(Location {
-                            row: right_box_pos.row,
-                            col: right_box_pos.col - 1,
-                        });
-                    }
-                }
-                Movement::Right => {
-                    self.robot_pos.col += 1;
-                    for left_box_pos in pushed_boxes_left.iter() {
-                        self.box_pos_left.remove(left_box_pos);
-                    }
-                    for left_box_pos in pushed_boxes_left {
-                        self.box_pos_left.insert(Location {
-                            row: left_box_pos.row,
-                            col: left_box_pos.col + 1,
-                        });
-                    }
-                    for right_box_pos in pushed_boxes_right.iter() {
-                        self.box_pos_right.remove(right_box_pos);
-                    }
-                    for right_box_pos in pushed_boxes_right {
-                        self.box_pos_right.insert(Location {
-                            row: right_box_pos.row,
-                            col: right_box_pos.col + 1,
-                        });
-                    }
-                }
-            };
+
+        Some(pushed_boxes)
+    }
+
+    /// Moves the robot if possible, pushing boxes along; returns whether the robot moved.
+    fn move_robot(&mut self, movement: Movement) -> bool {
+        self.apply_movement(movement) != MoveEffect::Blocked
+    }
+
+    fn apply_movement(&mut self, movement: Movement) -> MoveEffect {
+        let Some(pushed_boxes) = self.pushed_boxes(movement) else {
+            return MoveEffect::Blocked;
+        };
+        self.shift(movement.delta(), &pushed_boxes);
+        if cfg!(debug_assertions) {
+            self.check_invariants_for(&pushed_boxes)
+                .expect("Warehouse invariant broken after move");
+        }
+
+        MoveEffect::Moved { pushed_boxes }
+    }
+
+    /// Reverts a movement with the given effect, which must have been the last one applied.
+    fn revert_movement(&mut self, movement: Movement, effect: &MoveEffect) {
+        if let MoveEffect::Moved { pushed_boxes } = effect {
+            let (row_delta, col_delta) = movement.delta();
+            self.shift((-row_delta, -col_delta), pushed_boxes);
+        }
+    }
+
+    /// Moves the robot and the given boxes by one step.
+    fn shift(&mut self, (row_delta, col_delta): (i32, i32), boxes: &[usize]) {
+        for &box_id in boxes.iter() {
+            self.remove_box(box_id);
         }
+        for &box_id in boxes.iter() {
+            self.box_pos[box_id].row += row_delta;
+            self.box_pos[box_id].col += col_delta;
+            self.place_box(box_id);
+        }
+        self.robot_pos.row += row_delta;
+        self.robot_pos.col += col_delta;
+    }
+
+    /// Renders the warehouse like the puzzle input; boxes wider than two tiles are drawn as
+    /// `[==]`.
+    fn render(&self) -> String {
+        let max_row = self.wall_pos.iter().map(|wall| wall.row).max().unwrap_or(0);
+        let max_col = self.wall_pos.iter().map(|wall| wall.col).max().unwrap_or(0);
+
+        let mut grid = vec![vec!['.'; max_col as usize + 1]; max_row as usize + 1];
+        for wall in self.wall_pos.iter() {
+            grid[wall.row as usize][wall.col as usize] = '#';
+        }
+        for box_id in 0..self.box_pos.len() {
+            for (offset, tile) in self.tiles_of_box(box_id).enumerate() {
+                grid[tile.row as usize][tile.col as usize] = match offset {
+                    _ if self.box_width == 1 => 'O',
+                    0 => '[',
+                    _ if offset as i32 == self.box_width - 1 => ']',
+                    _ => '=',
+                };
+            }
+        }
+        grid[self.robot_pos.row as usize][self.robot_pos.col as usize] = '@';
+
+        grid.iter()
+            .map(|line| line.iter().collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+enum MoveEffect {
+    // the robot moved, pushing these boxes (possibly none) along
+    Moved { pushed_boxes: Vec<usize> },
+    // the robot or a box it pushed ran into a wall, so nothing moved
+    Blocked,
+}
+
+struct MoveRecord {
+    movement: Movement,
+    effect: MoveEffect,
+}
+
+/// Steps through a list of movements, recording the effect of every move so that moves can be
+/// undone and the warehouse inspected at any point.
+struct Replay {
+    warehouse: Warehouse,
+    movements: Vec<Movement>,
+    // the records of the moves applied so far, one per movement
+    history: Vec<MoveRecord>,
+}
+impl Replay {
+    fn new(warehouse: Warehouse, movements: Vec<Movement>) -> Replay {
+        Replay {
+            warehouse,
+            movements,
+            history: Vec::new(),
+        }
+    }
+
+    /// The number of moves that have been applied.
+    fn current_move(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Applies the next movement, if there is one left.
+    fn redo(&mut self) -> Option<&MoveRecord> {
+        let movement = *self.movements.get(self.history.len())?;
+        let effect = self.warehouse.apply_movement(movement);
+        self.history.push(MoveRecord { movement, effect });
+        self.history.last()
+    }
+
+    /// Reverts the last applied movement, if there is one.
+    fn undo(&mut self) -> Option<MoveRecord> {
+        let record = self.history.pop()?;
+        self.warehouse
+            .revert_movement(record.movement, &record.effect);
+        Some(record)
+    }
+
+    /// Applies or reverts movements until exactly `num_moves` moves have been applied.
+    fn jump_to(&mut self, num_moves: usize) {
+        let num_moves = num_moves.min(self.movements.len());
+        while self.current_move() > num_moves {
+            self.undo();
+        }
+        while self.current_move() < num_moves {
+            self.redo();
+        }
+    }
+
+    fn render(&self) -> String {
+        self.warehouse.render()
     }
 }
 
@@ -348,6 +420,51 @@ fn parse_movements(movement_input: &str) -> Vec<Movement> {
     movements
 }
 
+/// Writes statistics about a finished replay and the warehouse before and after the move that
+/// pushed the most boxes at once.
+fn write_replay_summary(replay: &mut Replay, filepath: &str) -> Result<(), Error> {
+    let num_blocked = replay
+        .history
+        .iter()
+        .filter(|record| record.effect == MoveEffect::Blocked)
+        .count();
+    let largest_push = replay
+        .history
+        .iter()
+        .enumerate()
+        .filter_map(|(move_idx, record)| match &record.effect {
+            MoveEffect::Moved { pushed_boxes } => Some((move_idx, pushed_boxes.len())),
+            MoveEffect::Blocked => None,
+        })
+        .max_by_key(|(move_idx, num_pushed)| (*num_pushed, std::cmp::Reverse(*move_idx)));
+
+    let mut summary_file = fs::File::create(filepath)?;
+    writeln!(
+        summary_file,
+        "{} of {} moves were blocked.",
+        num_blocked,
+        replay.current_move()
+    )?;
+    if let Some((move_idx, num_pushed)) = largest_push {
+        writeln!(
+            summary_file,
+            "Move {} pushed the most boxes at once ({}).",
+            move_idx + 1,
+            num_pushed
+        )?;
+        replay.jump_to(move_idx);
+        writeln!(summary_file)?;
+        writeln!(summary_file, "Before move {}:", move_idx + 1)?;
+        writeln!(summary_file, "{}", replay.render())?;
+        replay.redo();
+        writeln!(summary_file)?;
+        writeln!(summary_file, "After move {}:", move_idx + 1)?;
+        writeln!(summary_file, "{}", replay.render())?;
+    }
+
+    Ok(())
+}
+
 pub fn run() -> Result<(), Error> {
     let _ = task1();
     let _ = task2();
@@ -363,7 +480,7 @@ fn task1() -> Result<(), Error> {
     let input_data = fs::read_to_string("input_data/day15_input.txt")?;
     let (map_input, movements_input) = input_data.split_once("\n\n").unwrap();
 
-    let mut map = Map::from_str(map_input);
+    let mut map = Warehouse::from_str(map_input, 1)?;
     let movements = parse_movements(movements_input);
 
     for movement in movements.iter() {
@@ -389,14 +506,22 @@ fn task2() -> Result<(), Error> {
     let input_data = fs::read_to_string("input_data/day15_input.txt")?;
     let (map_input, movements_input) = input_data.split_once("\n\n").unwrap();
 
-    let mut map = WideMap::from_str(map_input);
-    let movements = parse_movements(movements_input);
-
-    for movement in movements.iter() {
-        map.move_robot(*movement);
+    let wide_warehouse = Warehouse::from_str(map_input, 1)?.widen();
+    wide_warehouse.check_invariants()?;
+    // the rendered map can be read back in, which is how wide maps appear in the puzzle
+    let rendered_map = wide_warehouse.render();
+    if Warehouse::from_str(&rendered_map, 1)?.render() != rendered_map {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "The wide map changes when reading it back in",
+        ));
     }
 
-    let gps_coord_sum = map.sum_box_gps_coords();
+    let mut replay = Replay::new(wide_warehouse, parse_movements(movements_input));
+    while replay.redo().is_some() {}
+
+    let gps_coord_sum = replay.warehouse.sum_box_gps_coords();
+    write_replay_summary(&mut replay, "solutions/day15_task2_replay.txt")?;
 
     let mut solution_file = fs::OpenOptions::new()
         .append(true)
@@ -412,3 +537,39 @@ fn task2() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn widen_matches_puzzle_rendering() {
+        let narrow_map = "#######
+#...#.#
+#.....#
+#..OO@#
+#..O..#
+#.....#
+#######";
+        let wide_map = "##############
+##......##..##
+##..........##
+##....[][]@.##
+##....[]....##
+##..........##
+##############";
+
+        let wide_warehouse = Warehouse::from_str(narrow_map, 1).unwrap().widen();
+        assert!(wide_warehouse.check_invariants().is_ok());
+        assert_eq!(wide_warehouse.render(), wide_map);
+        assert_eq!(Warehouse::from_str(wide_map, 1).unwrap().render(), wide_map);
+    }
+
+    #[test]
+    fn malformed_boxes_are_errors() {
+        assert!(Warehouse::from_str("#.]..#", 1).is_err());
+        assert!(Warehouse::from_str("#.[..#", 1).is_err());
+        assert!(Warehouse::from_str("#[]O.#", 1).is_err());
+        assert!(Warehouse::from_str("#[[].#", 1).is_err());
+    }
+}
@@ -4,8 +4,8 @@ use std::collections::BinaryHeap; // I want to only use the standard library
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::hash::Hash;
-use std::io::Error;
 use std::io::Write;
+use std::io::{Error, ErrorKind};
 
 #[derive(Eq, Hash, PartialEq, Clone, Copy)]
 enum Orientation {
@@ -14,6 +14,81 @@ enum Orientation {
     South,
     West,
 }
+impl Orientation {
+    fn turned_left(self) -> Orientation {
+        match self {
+            Orientation::North => Orientation::West,
+            Orientation::East => Orientation::North,
+            Orientation::South => Orientation::East,
+            Orientation::West => Orientation::South,
+        }
+    }
+
+    fn turned_right(self) -> Orientation {
+        match self {
+            Orientation::North => Orientation::East,
+            Orientation::East => Orientation::South,
+            Orientation::South => Orientation::West,
+            Orientation::West => Orientation::North,
+        }
+    }
+
+    fn step(self, location: Location) -> Location {
+        match self {
+            Orientation::North => Location {
+                x: location.x,
+                y: location.y - 1,
+            },
+            Orientation::East => Location {
+                x: location.x + 1,
+                y: location.y,
+            },
+            Orientation::South => Location {
+                x: location.x,
+                y: location.y + 1,
+            },
+            Orientation::West => Location {
+                x: location.x - 1,
+                y: location.y,
+            },
+        }
+    }
+}
+
+#[derive(Eq, Hash, PartialEq, Clone, Copy, Debug)]
+enum Action {
+    Forward,
+    Left,
+    Right,
+    UTurn,
+}
+
+/// The costs of the reindeer's actions. Without a U-turn cost the reindeer has to turn twice,
+/// and `tile_costs` are charged on top of `step` when moving onto a tile.
+struct CostModel {
+    step: u32,
+    turn: u32,
+    u_turn: Option<u32>,
+    tile_costs: HashMap<Location, u32>,
+}
+impl CostModel {
+    fn puzzle_costs() -> CostModel {
+        CostModel {
+            step: 1,
+            turn: 1000,
+            u_turn: None,
+            tile_costs: HashMap::new(),
+        }
+    }
+}
+
+struct MazeSolution {
+    lowest_cost: u32,
+    // every location on at least one of the best paths
+    best_seats: HashSet<Location>,
+    // the actions along one of the best paths
+    actions: Vec<Action>,
+}
 
 #[derive(Eq, Hash, PartialEq, Clone, Copy)]
 struct Location {
@@ -62,8 +137,7 @@ struct Maze {
     wall_pos: HashSet<Location>,
     start_state: ReindeerState,
     end_location: Location,
-    turning_cost: u32,
-    moving_cost: u32,
+    costs: CostModel,
 }
 impl Maze {
     fn from_str(input: &str) -> Maze {
@@ -105,21 +179,77 @@ impl Maze {
             wall_pos,
             start_state,
             end_location,
-            turning_cost: 1000,
-            moving_cost: 1,
+            costs: CostModel::puzzle_costs(),
         }
     }
 
-    fn dijkstra(&self) -> (i32, HashSet<Location>) {
+    /// The states the reindeer can reach from `reindeer_state` with a single action, together
+    /// with the action and its cost.
+    fn successors(&self, reindeer_state: ReindeerState) -> Vec<(ReindeerState, Action, u32)> {
+        let turn_to = |orientation| ReindeerState {
+            location: reindeer_state.location,
+            orientation,
+        };
+        let orientation = reindeer_state.orientation;
+
+        let mut successors = vec![
+            (
+                turn_to(orientation.turned_left()),
+                Action::Left,
+                self.costs.turn,
+            ),
+            (
+                turn_to(orientation.turned_right()),
+                Action::Right,
+                self.costs.turn,
+            ),
+        ];
+        if let Some(u_turn_cost) = self.costs.u_turn {
+            successors.push((
+                turn_to(orientation.turned_left().turned_left()),
+                Action::UTurn,
+                u_turn_cost,
+            ));
+        }
+
+        let forward_location = orientation.step(reindeer_state.location);
+        if !self.wall_pos.contains(&forward_location) {
+            let tile_cost = self
+                .costs
+                .tile_costs
+                .get(&forward_location)
+                .copied()
+                .unwrap_or(0);
+            successors.push((
+                ReindeerState {
+                    location: forward_location,
+                    orientation,
+                },
+                Action::Forward,
+                self.costs.step + tile_cost,
+            ));
+        }
+
+        successors
+    }
+
+    /// Finds the lowest cost from start to end, all the locations on any of the cheapest paths,
+    /// and the actions along one of them. Returns None if the end cannot be reached.
+    fn dijkstra(&self) -> Option<MazeSolution> {
         let mut priority_queue: BinaryHeap<HeapEntry> = BinaryHeap::new();
         let mut state_costs: HashMap<ReindeerState, u32> = HashMap::new();
-        let mut predecessors: HashMap<ReindeerState, Vec<ReindeerState>> = HashMap::new();
+        let mut predecessors: HashMap<ReindeerState, Vec<(ReindeerState, Action)>> = HashMap::new();
         state_costs.insert(self.start_state, 0);
         priority_queue.push(HeapEntry {
             reindeer_state: self.start_state,
             cost: 0,
         });
         predecessors.insert(self.start_state, Vec::new());
+        // a single parent per state, only replaced when the cost of the state goes down, so
+        // following them always ends at the start even with zero-cost actions
+        let mut parents: HashMap<ReindeerState, (ReindeerState, Action)> = HashMap::new();
+        let mut lowest_cost: Option<u32> = None;
+        let mut end_states: Vec<ReindeerState> = Vec::new();
 
         while let Some(HeapEntry {
             reindeer_state,
@@ -131,187 +261,176 @@ impl Maze {
                     continue; // ignore stale distance in heap
                 }
             }
-            if reindeer_state.location == self.end_location {
-                let mut any_shortest_path_locations: HashSet<Location> = HashSet::new();
-                let mut states_to_add: Vec<ReindeerState> = vec![reindeer_state];
-
-                while let Some(state_to_add) = states_to_add.pop() {
-                    any_shortest_path_locations.insert(state_to_add.location);
-                    for predecessor in predecessors.get(&state_to_add).unwrap() {
-                        states_to_add.push(*predecessor);
-                    }
-                }
-
-                return (cost as i32, any_shortest_path_locations);
+            if lowest_cost.is_some_and(|lowest_cost| cost > lowest_cost) {
+                break; // every state on a cheapest path has been reached
             }
-
-            let turn_left;
-            let turn_right;
-            let move_forward;
-
-            match reindeer_state.orientation {
-                Orientation::North => {
-                    turn_left = ReindeerState {
-                        location: reindeer_state.location,
-                        orientation: Orientation::West,
-                    };
-                    turn_right = ReindeerState {
-                        location: reindeer_state.location,
-                        orientation: Orientation::East,
-                    };
-                    move_forward = ReindeerState {
-                        location: Location {
-                            x: reindeer_state.location.x,
-                            y: reindeer_state.location.y - 1,
-                        },
-                        orientation: reindeer_state.orientation,
-                    };
-                }
-                Orientation::East => {
-                    turn_left = ReindeerState {
-                        location: reindeer_state.location,
-                        orientation: Orientation::North,
-                    };
-                    turn_right = ReindeerState {
-                        location: reindeer_state.location,
-                        orientation: Orientation::South,
-                    };
-                    move_forward = ReindeerState {
-                        location: Location {
-                            x: reindeer_state.location.x + 1,
-                            y: reindeer_state.location.y,
-                        },
-                        orientation: reindeer_state.orientation,
-                    };
-                }
-                Orientation::South => {
-                    turn_left = ReindeerState {
-                        location: reindeer_state.location,
-                        orientation: Orientation::East,
-                    };
-                    turn_right = ReindeerState {
-                        location: reindeer_state.location,
-                        orientation: Orientation::West,
-                    };
-                    move_forward = ReindeerState {
-                        location: Location {
-                            x: reindeer_state.location.x,
-                            y: reindeer_state.location.y + 1,
-                        },
-                        orientation: reindeer_state.orientation,
-                    };
-                }
-                Orientation::West => {
-                    turn_left = ReindeerState {
-                        location: reindeer_state.location,
-                        orientation: Orientation::South,
-                    };
-                    turn_right = ReindeerState {
-                        location: reindeer_state.location,
-                        orientation: Orientation::North,
-                    };
-                    move_forward = ReindeerState {
-                        location: Location {
-                            x: reindeer_state.location.x - 1,
-                            y: reindeer_state.location.y,
-                        },
-                        orientation: reindeer_state.orientation,
-                    };
-                }
+            if reindeer_state.location == self.end_location {
+                // the end may be reached in other orientations at the same cost
+                lowest_cost = Some(cost);
+                end_states.push(reindeer_state);
+                continue;
             }
 
-            let turn_left_cost = cost + self.turning_cost;
-            let turn_right_cost = cost + self.turning_cost;
-            let move_forward_cost = cost + self.moving_cost;
-
-            if let Some(&prev_turn_left_cost) = state_costs.get(&turn_left) {
-                match turn_left_cost.cmp(&prev_turn_left_cost) {
-                    Less => {
-                        state_costs.insert(turn_left, turn_left_cost);
+            for (next_state, action, action_cost) in self.successors(reindeer_state) {
+                let next_cost = cost + action_cost;
+                match state_costs
+                    .get(&next_state)
+                    .map(|prev_cost| next_cost.cmp(prev_cost))
+                {
+                    None | Some(Less) => {
+                        state_costs.insert(next_state, next_cost);
                         priority_queue.push(HeapEntry {
-                            cost: turn_left_cost,
-                            reindeer_state: turn_left,
+                            cost: next_cost,
+                            reindeer_state: next_state,
                         });
-                        predecessors.insert(turn_left, vec![reindeer_state]);
+                        predecessors.insert(next_state, vec![(reindeer_state, action)]);
+                        parents.insert(next_state, (reindeer_state, action));
                     }
-                    Equal => {
+                    Some(Equal) => {
                         predecessors
-                            .get_mut(&turn_left)
+                            .get_mut(&next_state)
                             .unwrap()
-                            .push(reindeer_state);
+                            .push((reindeer_state, action));
                     }
-                    Greater => {}
+                    Some(Greater) => {}
                 }
-            } else {
-                state_costs.insert(turn_left, turn_left_cost);
-                priority_queue.push(HeapEntry {
-                    cost: cost + self.turning_cost,
-                    reindeer_state: turn_left,
-                });
-                predecessors.insert(turn_left, vec![reindeer_state]);
             }
+        }
 
-            if let Some(&prev_turn_right_cost) = state_costs.get(&turn_right) {
-                match turn_right_cost.cmp(&prev_turn_right_cost) {
-                    Less => {
-                        state_costs.insert(turn_right, turn_right_cost);
-                        priority_queue.push(HeapEntry {
-                            cost: turn_right_cost,
-                            reindeer_state: turn_right,
-                        });
-                        predecessors.insert(turn_right, vec![reindeer_state]);
-                    }
-                    Equal => {
-                        predecessors
-                            .get_mut(&turn_right)
-                            .unwrap()
-                            .push(reindeer_state);
-                    }
-                    Greater => {}
-                }
-            } else {
-                state_costs.insert(turn_right, cost + self.turning_cost);
-                priority_queue.push(HeapEntry {
-                    cost: cost + self.turning_cost,
-                    reindeer_state: turn_right,
-                });
-                predecessors.insert(turn_right, vec![reindeer_state]);
+        let lowest_cost = lowest_cost?; // end location unreachable
+
+        let mut best_seats: HashSet<Location> = HashSet::new();
+        let mut visited_states: HashSet<ReindeerState> = HashSet::new();
+        let mut states_to_add: Vec<ReindeerState> = end_states.clone();
+
+        while let Some(state_to_add) = states_to_add.pop() {
+            if !visited_states.insert(state_to_add) {
+                continue;
+            }
+            best_seats.insert(state_to_add.location);
+            for (predecessor, _) in predecessors.get(&state_to_add).unwrap() {
+                states_to_add.push(*predecessor);
             }
+        }
 
-            if !self.wall_pos.contains(&move_forward.location) {
-                if let Some(&prev_move_forward_cost) = state_costs.get(&move_forward) {
-                    match move_forward_cost.cmp(&prev_move_forward_cost) {
-                        Less => {
-                            state_costs.insert(move_forward, cost + self.turning_cost);
-                            priority_queue.push(HeapEntry {
-                                cost: move_forward_cost,
-                                reindeer_state: move_forward,
-                            });
-                            predecessors.insert(move_forward, vec![reindeer_state]);
-                        }
-                        Equal => {
-                            predecessors
-                                .get_mut(&move_forward)
-                                .unwrap()
-                                .push(reindeer_state);
-                        }
-                        Greater => {}
+        // follow the parents back to the start for one of the best paths
+        let mut actions = Vec::new();
+        let mut current_state = end_states[0];
+        while let Some(&(parent, action)) = parents.get(&current_state) {
+            actions.push(action);
+            current_state = parent;
+        }
+        actions.reverse();
+
+        Some(MazeSolution {
+            lowest_cost,
+            best_seats,
+            actions,
+        })
+    }
+}
+
+/// What to draw on top of the maze when rendering it.
+struct RenderOptions {
+    // mark every tile on any of the best paths with 'O'
+    best_seats: bool,
+    // draw the orientation of the reindeer along the path given by the solution's actions
+    path_arrows: bool,
+    // write the rendering to this file as well
+    output_path: Option<String>,
+}
+
+impl Maze {
+    /// Renders the maze with the solution drawn in, like the pictures in the puzzle statement.
+    fn render(&self, solution: &MazeSolution, options: &RenderOptions) -> Result<String, Error> {
+        let max_x = self.wall_pos.iter().map(|wall| wall.x).max().unwrap_or(0);
+        let max_y = self.wall_pos.iter().map(|wall| wall.y).max().unwrap_or(0);
+
+        let mut grid = vec![vec!['.'; max_x as usize + 1]; max_y as usize + 1];
+        for wall in self.wall_pos.iter() {
+            grid[wall.y as usize][wall.x as usize] = '#';
+        }
+        if options.best_seats {
+            for seat in solution.best_seats.iter() {
+                grid[seat.y as usize][seat.x as usize] = 'O';
+            }
+        }
+        if options.path_arrows {
+            let mut reindeer_state = self.start_state;
+            for action in solution.actions.iter() {
+                let location = reindeer_state.location;
+                match action {
+                    Action::Forward => {
+                        grid[location.y as usize][location.x as usize] =
+                            match reindeer_state.orientation {
+                                Orientation::North => '^',
+                                Orientation::East => '>',
+                                Orientation::South => 'v',
+                                Orientation::West => '<',
+                            };
+                        reindeer_state.location = reindeer_state.orientation.step(location);
+                    }
+                    Action::Left => {
+                        reindeer_state.orientation = reindeer_state.orientation.turned_left()
+                    }
+                    Action::Right => {
+                        reindeer_state.orientation = reindeer_state.orientation.turned_right()
+                    }
+                    Action::UTurn => {
+                        reindeer_state.orientation =
+                            reindeer_state.orientation.turned_left().turned_left()
                     }
-                } else {
-                    state_costs.insert(move_forward, cost + self.moving_cost);
-                    priority_queue.push(HeapEntry {
-                        cost: move_forward_cost,
-                        reindeer_state: move_forward,
-                    });
-                    predecessors.insert(move_forward, vec![reindeer_state]);
                 }
             }
         }
+        let start_location = self.start_state.location;
+        grid[start_location.y as usize][start_location.x as usize] = 'S';
+        grid[self.end_location.y as usize][self.end_location.x as usize] = 'E';
+
+        let rendering = grid
+            .iter()
+            .map(|line| line.iter().collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        if let Some(output_path) = &options.output_path {
+            let mut output_file = fs::File::create(output_path)?;
+            writeln!(output_file, "{}", rendering)?;
+        }
 
-        (i32::MAX, HashSet::new()) // end location unreachable
+        Ok(rendering)
     }
 }
 
+/// Writes actions compactly, e.g. `F3 L F12 R`.
+fn format_actions(actions: &[Action]) -> String {
+    let mut groups: Vec<(Action, usize)> = Vec::new();
+    for &action in actions {
+        match groups.last_mut() {
+            Some((last_action, count)) if *last_action == action => *count += 1,
+            _ => groups.push((action, 1)),
+        }
+    }
+
+    groups
+        .iter()
+        .map(|(action, count)| {
+            let symbol = match action {
+                Action::Forward => "F",
+                Action::Left => "L",
+                Action::Right => "R",
+                Action::UTurn => "U",
+            };
+            if *count == 1 {
+                symbol.to_string()
+            } else {
+                format!("{}{}", symbol, count)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
 pub fn run() -> Result<(), Error> {
     let _ = task1();
     let _ = task2();
@@ -327,7 +446,10 @@ fn task1() -> Result<(), Error> {
     let input_data = fs::read_to_string("input_data/day16_input.txt")?;
 
     let maze = Maze::from_str(&input_data);
-    let (lowest_cost, _) = maze.dijkstra();
+    let solution = maze
+        .dijkstra()
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "End location is unreachable"))?;
+    let lowest_cost = solution.lowest_cost;
 
     let mut solution_file = fs::File::create("solutions/day16_solution.txt")?;
     writeln!(solution_file, "Solution for Task 1 of Day 16:")?;
@@ -336,6 +458,11 @@ fn task1() -> Result<(), Error> {
         "The lowest cost to move from Start to End is {}.",
         lowest_cost
     )?;
+    writeln!(
+        solution_file,
+        "One of the cheapest paths is: {}",
+        format_actions(&solution.actions)
+    )?;
 
     Ok(())
 }
@@ -346,7 +473,19 @@ fn task2() -> Result<(), Error> {
     let input_data = fs::read_to_string("input_data/day16_input.txt")?;
 
     let maze = Maze::from_str(&input_data);
-    let (_, any_best_path_locations) = maze.dijkstra();
+    let solution = maze
+        .dijkstra()
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "End location is unreachable"))?;
+    let any_best_path_locations = &solution.best_seats;
+
+    maze.render(
+        &solution,
+        &RenderOptions {
+            best_seats: true,
+            path_arrows: true,
+            output_path: Some("solutions/day16_task2_best_seats.txt".to_string()),
+        },
+    )?;
 
     let mut solution_file = fs::OpenOptions::new()
         .append(true)
@@ -362,3 +501,59 @@ fn task2() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "###############
+#.......#....E#
+#.#.###.#.###.#
+#.....#.#...#.#
+#.###.#####.#.#
+#.#.#.......#.#
+#.#.#####.###.#
+#...........#.#
+###.#.#####.#.#
+#...#.....#.#.#
+#.#.#.###.#.#.#
+#.....#...#.#.#
+#.###.#.#.#.#.#
+#S..#.....#...#
+###############";
+
+    #[test]
+    fn example_with_puzzle_costs() {
+        let solution = Maze::from_str(EXAMPLE).dijkstra().unwrap();
+        assert_eq!(solution.lowest_cost, 7036);
+        assert_eq!(solution.best_seats.len(), 45);
+        assert_eq!(
+            format_actions(&solution.actions),
+            "L F2 R F4 L F4 R F6 R F6 L F2 L F12"
+        );
+    }
+
+    #[test]
+    fn every_end_orientation_counts() {
+        let mut maze = Maze::from_str("#####\n#..E#\n#...#\n#S..#\n#####");
+        maze.costs.turn = 0;
+        let solution = maze.dijkstra().unwrap();
+
+        assert_eq!(solution.lowest_cost, 4);
+        assert_eq!(solution.best_seats.len(), 9);
+    }
+
+    #[test]
+    fn zero_cost_turns_terminate() {
+        let mut maze = Maze::from_str(EXAMPLE);
+        maze.costs.turn = 0;
+        let solution = maze.dijkstra().unwrap();
+
+        let num_steps = solution
+            .actions
+            .iter()
+            .filter(|&&action| action == Action::Forward)
+            .count();
+        assert_eq!(solution.lowest_cost, num_steps as u32);
+    }
+}
@@ -1,6 +1,8 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::io::Error;
 use std::io::Write;
+use std::io::{Error, ErrorKind};
 
 fn program_from_str(input: &str) -> Vec<u32> {
     let program_str;
@@ -13,6 +15,85 @@ fn program_from_str(input: &str) -> Vec<u32> {
     program_str.split(',').flat_map(|val| val.parse()).collect()
 }
 
+enum StepOutcome {
+    Halted,
+    Executed { output: Option<u64> },
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Breakpoint {
+    // stop before executing the instruction at this address
+    InstructionPointer(u32),
+    // stop after outputting this value, or any value if None
+    Output(Option<u64>),
+}
+
+#[derive(Default)]
+struct RunOptions {
+    trace: bool,
+    breakpoints: Vec<Breakpoint>,
+    // return an error after this many steps, so programs that don't halt can't hang
+    cycle_limit: Option<usize>,
+}
+
+/// The state of the computer before executing an instruction, and what that instruction output.
+#[derive(Debug)]
+struct TraceEntry {
+    instruction: Instruction,
+    reg_a: u64,
+    reg_b: u64,
+    reg_c: u64,
+    output: Option<u64>,
+}
+impl TraceEntry {
+    fn format(&self) -> String {
+        let mut line = format!(
+            "ip {:>2}: {:<6} A={} B={} C={}",
+            self.instruction.address,
+            format!(
+                "{} {}",
+                self.instruction.mnemonic(),
+                self.instruction.operand_str()
+            ),
+            self.reg_a,
+            self.reg_b,
+            self.reg_c
+        );
+        if let Some(out_val) = self.output {
+            line.push_str(&format!(" -> out {}", out_val));
+        }
+        line
+    }
+}
+
+#[derive(Debug)]
+struct RunResult {
+    output: Vec<u64>,
+    // only filled in when tracing
+    trace: Vec<TraceEntry>,
+    num_steps: usize,
+    // the breakpoint that stopped the run, None if the program halted
+    breakpoint: Option<Breakpoint>,
+}
+
+/// The error inside the `Error` returned when a run reaches its cycle limit, with the output and
+/// trace of the run so far.
+#[derive(Debug)]
+struct CycleLimitReached {
+    instruction_pointer: u32,
+    partial_result: RunResult,
+}
+impl fmt::Display for CycleLimitReached {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Program did not halt within {} steps, stopped at instruction pointer {}",
+            self.partial_result.num_steps, self.instruction_pointer
+        )
+    }
+}
+impl std::error::Error for CycleLimitReached {}
+
 #[derive(Copy, Clone)]
 struct Computer {
     // the instructions say that registers can be any integer, but if the initial values are all >=0, then they will stay so.
@@ -21,6 +102,9 @@ struct Computer {
     reg_b: u64,
     reg_c: u64,
     instruction_pointer: u32,
+    // the instruction pointer a breakpoint stopped the last run at, so resuming doesn't stop there
+    // again right away
+    paused_at: Option<u32>,
 }
 impl Computer {
     fn from_config(config: &str) -> Computer {
@@ -46,6 +130,7 @@ impl Computer {
             reg_b,
             reg_c,
             instruction_pointer: 0,
+            paused_at: None,
         }
     }
     fn operand_to_combo(&self, operand: u32) -> u64 {
@@ -112,67 +197,551 @@ impl Computer {
         }
         (false, None)
     }
+    /// Executes the instruction at the instruction pointer, if there is one.
+    fn step(&mut self, program: &[u32]) -> StepOutcome {
+        if self.instruction_pointer as usize + 1 >= program.len() {
+            return StepOutcome::Halted;
+        }
+        let instruction = program[self.instruction_pointer as usize];
+        let operand = program[self.instruction_pointer as usize + 1];
+
+        let (jumped, output) = self.execute_instruction(instruction, operand);
+        if !jumped {
+            self.instruction_pointer += 2;
+        }
+        StepOutcome::Executed { output }
+    }
+
+    /// Runs the program until it halts or hits a breakpoint. Running again afterwards resumes
+    /// from the breakpoint.
+    fn run(&mut self, program: &[u32], options: &RunOptions) -> Result<RunResult, Error> {
+        let mut result = RunResult {
+            output: Vec::new(),
+            trace: Vec::new(),
+            num_steps: 0,
+            breakpoint: None,
+        };
+
+        loop {
+            let is_resuming =
+                result.num_steps == 0 && self.paused_at == Some(self.instruction_pointer);
+            if !is_resuming {
+                if let Some(&breakpoint) = options.breakpoints.iter().find(|breakpoint| {
+                    **breakpoint == Breakpoint::InstructionPointer(self.instruction_pointer)
+                }) {
+                    self.paused_at = Some(self.instruction_pointer);
+                    result.breakpoint = Some(breakpoint);
+                    return Ok(result);
+                }
+            }
+            if options
+                .cycle_limit
+                .is_some_and(|cycle_limit| result.num_steps >= cycle_limit)
+            {
+                return Err(Error::other(CycleLimitReached {
+                    instruction_pointer: self.instruction_pointer,
+                    partial_result: result,
+                }));
+            }
+
+            let state_before = *self;
+            let output = match self.step(program) {
+                StepOutcome::Halted => return Ok(result),
+                StepOutcome::Executed { output } => output,
+            };
+            self.paused_at = None;
+            result.num_steps += 1;
+
+            if options.trace {
+                result.trace.push(TraceEntry {
+                    instruction: Instruction {
+                        address: state_before.instruction_pointer,
+                        opcode: program[state_before.instruction_pointer as usize],
+                        operand: program[state_before.instruction_pointer as usize + 1],
+                    },
+                    reg_a: state_before.reg_a,
+                    reg_b: state_before.reg_b,
+                    reg_c: state_before.reg_c,
+                    output,
+                });
+            }
+            if let Some(out_val) = output {
+                result.output.push(out_val);
+                if let Some(&breakpoint) = options.breakpoints.iter().find(|breakpoint| {
+                    matches!(breakpoint, Breakpoint::Output(value) if value.is_none_or(|value| value == out_val))
+                }) {
+                    result.breakpoint = Some(breakpoint);
+                    return Ok(result);
+                }
+            }
+        }
+    }
+
     fn execute_program(&mut self, program: Vec<u32>) -> Vec<u64> {
         let mut output: Vec<u64> = Vec::new();
 
-        while self.instruction_pointer < program.len() as u32 - 1 {
-            let instruction = program[self.instruction_pointer as usize];
-            let operand = program[self.instruction_pointer as usize + 1];
-
-            let (jumped, output_maybe) = self.execute_instruction(instruction, operand);
+        while let StepOutcome::Executed {
+            output: output_maybe,
+        } = self.step(&program)
+        {
             if let Some(out_val) = output_maybe {
                 output.push(out_val);
             }
-            if !jumped {
-                self.instruction_pointer += 2;
-            }
         }
 
         output
     }
+}
 
-    fn get_first_program_output(&mut self, program: Vec<u32>) -> u64 {
-        while self.instruction_pointer < program.len() as u32 - 1 {
-            let instruction = program[self.instruction_pointer as usize];
-            let operand = program[self.instruction_pointer as usize + 1];
+const MNEMONICS: [&str; 8] = ["adv", "bxl", "bst", "jnz", "bxc", "out", "bdv", "cdv"];
 
-            let (jumped, output_maybe) = self.execute_instruction(instruction, operand);
-            if let Some(out_val) = output_maybe {
-                return out_val;
+/// A single decoded instruction of a program.
+#[derive(Copy, Clone, Debug)]
+struct Instruction {
+    address: u32,
+    opcode: u32,
+    operand: u32,
+}
+impl Instruction {
+    fn mnemonic(&self) -> &'static str {
+        MNEMONICS[self.opcode as usize]
+    }
+
+    fn takes_combo_operand(&self) -> bool {
+        matches!(self.opcode, 0 | 2 | 5 | 6 | 7)
+    }
+
+    /// The operand as it reads in source, i.e. combo operands 4 to 6 become the register names.
+    fn operand_str(&self) -> String {
+        if self.takes_combo_operand() {
+            match self.operand {
+                4 => "A".to_string(),
+                5 => "B".to_string(),
+                6 => "C".to_string(),
+                literal => literal.to_string(),
+            }
+        } else {
+            self.operand.to_string()
+        }
+    }
+
+    /// The value of a combo operand modulo 8 as an expression; literals are folded.
+    fn combo_mod_8_str(&self) -> String {
+        if self.operand < 4 {
+            self.operand.to_string()
+        } else {
+            format!("{} % 8", self.operand_str())
+        }
+    }
+
+    /// The instruction as a pseudo-code statement, without the jumps.
+    fn pseudo_code(&self) -> String {
+        match self.opcode {
+            0 => format!("A = A >> {};", self.operand_str()),
+            1 => format!("B = B ^ {};", self.operand),
+            2 => format!("B = {};", self.combo_mod_8_str()),
+            3 => format!("if A != 0 {{ goto {}; }}", self.operand),
+            4 => "B = B ^ C;".to_string(),
+            5 => format!("output({});", self.combo_mod_8_str()),
+            6 => format!("B = A >> {};", self.operand_str()),
+            7 => format!("C = A >> {};", self.operand_str()),
+            _ => unreachable!("opcodes are checked when disassembling"),
+        }
+    }
+}
+
+/// Decodes a program into instructions, checking that every opcode and combo operand is valid.
+fn disassemble(program: &[u32]) -> Result<Vec<Instruction>, Error> {
+    if !program.len().is_multiple_of(2) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "Program has odd length {}, the last opcode has no operand",
+                program.len()
+            ),
+        ));
+    }
+
+    program
+        .chunks(2)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let instruction = Instruction {
+                address: 2 * index as u32,
+                opcode: chunk[0],
+                operand: chunk[1],
+            };
+            if instruction.opcode > 7 || instruction.operand > 7 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "Invalid 3-bit value at address {}: {},{}",
+                        instruction.address, instruction.opcode, instruction.operand
+                    ),
+                ));
             }
-            if !jumped {
-                self.instruction_pointer += 2;
+            if instruction.takes_combo_operand() && instruction.operand == 7 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "Reserved combo operand 7 at address {} ({})",
+                        instruction.address,
+                        instruction.mnemonic()
+                    ),
+                ));
             }
+            Ok(instruction)
+        })
+        .collect()
+}
+
+/// Formats instructions one per line with their address as a comment, e.g. `bst A      ; 0`.
+fn format_listing(instructions: &[Instruction]) -> String {
+    instructions
+        .iter()
+        .map(|instruction| {
+            let source = format!("{} {}", instruction.mnemonic(), instruction.operand_str());
+            format!("{:<10} ; {}\n", source, instruction.address)
+        })
+        .collect()
+}
+
+/// Turns a program into pseudo-code. Programs that are a single loop, i.e. that only have a
+/// `jnz 0` as their last instruction, become a do-while loop; everything else gets gotos.
+fn decompile(program: &[u32]) -> Result<String, Error> {
+    let instructions = disassemble(program)?;
+
+    let jumps: Vec<&Instruction> = instructions
+        .iter()
+        .filter(|instruction| instruction.opcode == 3)
+        .collect();
+    let is_single_loop = jumps.len() == 1
+        && jumps[0].operand == 0
+        && jumps[0].address == instructions.last().map_or(0, |last| last.address);
+
+    let mut pseudo_code = String::new();
+    if is_single_loop {
+        pseudo_code.push_str("do {\n");
+        for instruction in instructions[..instructions.len() - 1].iter() {
+            pseudo_code.push_str(&format!("    {}\n", instruction.pseudo_code()));
+        }
+        pseudo_code.push_str("} while A != 0;\n");
+    } else {
+        for instruction in instructions.iter() {
+            pseudo_code.push_str(&format!(
+                "{:>3}: {}\n",
+                instruction.address,
+                instruction.pseudo_code()
+            ));
         }
-        0 // default value
     }
 
-    fn _check_output_program_copy(&mut self, program: Vec<u32>) -> bool {
-        let mut output: Vec<u64> = Vec::new();
+    Ok(pseudo_code)
+}
 
-        while self.instruction_pointer < program.len() as u32 - 1 {
-            let instruction = program[self.instruction_pointer as usize];
-            let operand = program[self.instruction_pointer as usize + 1];
+fn parse_operand_value(operand: &str, line_number: usize) -> Result<u32, Error> {
+    operand.parse().map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Line {}: invalid operand '{}'", line_number, operand),
+        )
+    })
+}
 
-            let (jumped, output_maybe) = self.execute_instruction(instruction, operand);
-            if let Some(out_val) = output_maybe {
-                if output.len() == program.len() || out_val != program[output.len()] as u64 {
-                    return false;
+/// Turns mnemonic source into a program, e.g. `adv 3`, `out B` or `jnz start`. Every line holds
+/// one instruction or a label definition like `start:`, and `;` starts a comment. This reads the
+/// listings written by `format_listing`.
+fn assemble(source: &str) -> Result<Vec<u32>, Error> {
+    let lines: Vec<(usize, &str)> = source
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.split(';').next().unwrap_or("").trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .collect();
+
+    let mut labels: HashMap<&str, u32> = HashMap::new();
+    let mut address = 0;
+    for &(line_number, line) in lines.iter() {
+        if let Some(label) = line.strip_suffix(':') {
+            if labels.insert(label.trim(), address).is_some() {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Line {}: label '{}' is defined twice", line_number, label),
+                ));
+            }
+        } else {
+            address += 2;
+        }
+    }
+
+    let mut program = Vec::new();
+    for &(line_number, line) in lines.iter() {
+        if line.ends_with(':') {
+            continue;
+        }
+        let (mnemonic, operand) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(mnemonic, operand)| (mnemonic, operand.trim()));
+        let Some(opcode) = MNEMONICS.iter().position(|&known| known == mnemonic) else {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Line {}: unknown instruction '{}'", line_number, mnemonic),
+            ));
+        };
+        let instruction = Instruction {
+            address: program.len() as u32,
+            opcode: opcode as u32,
+            operand: 0,
+        };
+
+        let operand = if instruction.takes_combo_operand() {
+            match operand {
+                "A" => 4,
+                "B" => 5,
+                "C" => 6,
+                "7" => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Line {}: combo operand 7 is reserved", line_number),
+                    ))
+                }
+                _ => {
+                    let value = parse_operand_value(operand, line_number)?;
+                    if value > 3 {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            format!(
+                                "Line {}: combo operand {} is out of range, use 0 to 3 or A, B, C",
+                                line_number, value
+                            ),
+                        ));
+                    }
+                    value
                 }
-                output.push(out_val);
             }
-            if !jumped {
-                self.instruction_pointer += 2;
+        } else if instruction.opcode == 4 && operand.is_empty() {
+            // bxc ignores its operand
+            0
+        } else if let Some(&label_address) = labels.get(operand) {
+            if instruction.opcode != 3 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Line {}: only jnz can jump to a label", line_number),
+                ));
             }
-        }
+            if label_address > 7 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "Line {}: label '{}' at address {} is out of range for a jump",
+                        line_number, operand, label_address
+                    ),
+                ));
+            }
+            label_address
+        } else {
+            let value = parse_operand_value(operand, line_number)?;
+            if value > 7 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "Line {}: literal operand {} is out of range, use 0 to 7",
+                        line_number, value
+                    ),
+                ));
+            }
+            value
+        };
+
+        program.push(instruction.opcode);
+        program.push(operand);
+    }
+
+    Ok(program)
+}
+
+fn format_program(program: &[u32]) -> String {
+    program
+        .iter()
+        .map(|val| val.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Assembles the examples from the puzzle description and checks that the input program survives
+/// disassembling and assembling again.
+fn write_assembler_examples() -> Result<(), Error> {
+    println!("Assembling example programs for Day 17...");
+
+    let input_data = fs::read_to_string("input_data/day17_input.txt")?;
+    let (_, program_str) = input_data.split_once("\n\n").unwrap();
+    let program = program_from_str(program_str);
+    if assemble(&format_listing(&disassemble(&program)?))? != program {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "The input program changes when disassembling and assembling it again",
+        ));
+    }
+
+    let mut examples_file = fs::File::create("solutions/day17_assembled_examples.txt")?;
+
+    let example_source = "adv 1\nout A\njnz 0\n";
+    let example_program = assemble(example_source)?;
+    let mut example_computer = Computer::from_config("Register A: 729");
+    let example_output = example_computer.execute_program(example_program.clone());
+    writeln!(examples_file, "{}", example_source)?;
+    writeln!(
+        examples_file,
+        "assembles to {}, and with A=729 outputs {}.",
+        format_program(&example_program),
+        example_output
+            .iter()
+            .map(|out| out.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    )?;
+
+    let quine_source = "start:\n    adv 3\n    out A\n    jnz start ; loop until A is 0\n";
+    let quine_program = assemble(quine_source)?;
+    let quine_computer = Computer::from_config("Register A: 2024");
+    writeln!(examples_file)?;
+    writeln!(examples_file, "{}", quine_source)?;
+    writeln!(
+        examples_file,
+        "assembles to {}, and outputs a copy of itself with A={}.",
+        format_program(&quine_program),
+        find_quine_start_values(&quine_computer, &quine_program)?
+            .iter()
+            .map(|start_value| start_value.to_string())
+            .collect::<Vec<_>>()
+            .join(" or A=")
+    )?;
+
+    Ok(())
+}
+
+fn write_disassembly() -> Result<(), Error> {
+    println!("Disassembling the program of Day 17...");
+
+    let input_data = fs::read_to_string("input_data/day17_input.txt")?;
+    let (_, program_str) = input_data.split_once("\n\n").unwrap();
+    let program = program_from_str(program_str);
+
+    let mut disassembly_file = fs::File::create("solutions/day17_disassembly.txt")?;
+    writeln!(disassembly_file, "Listing:")?;
+    write!(
+        disassembly_file,
+        "{}",
+        format_listing(&disassemble(&program)?)
+    )?;
+    writeln!(disassembly_file)?;
+    writeln!(disassembly_file, "Pseudo-code:")?;
+    write!(disassembly_file, "{}", decompile(&program)?)?;
+
+    Ok(())
+}
+
+fn write_trace() -> Result<(), Error> {
+    println!("Tracing the program of Day 17...");
+
+    let input_data = fs::read_to_string("input_data/day17_input.txt")?;
+    let (computer_config, program_str) = input_data.split_once("\n\n").unwrap();
+    let program = program_from_str(program_str);
+
+    let mut trace_file = fs::File::create("solutions/day17_task1_trace.txt")?;
+
+    let mut computer = Computer::from_config(computer_config);
+    let traced_run = computer.run(
+        &program,
+        &RunOptions {
+            trace: true,
+            cycle_limit: Some(100_000),
+            ..RunOptions::default()
+        },
+    )?;
+    writeln!(
+        trace_file,
+        "The program halted after {} steps:",
+        traced_run.num_steps
+    )?;
+    for entry in traced_run.trace.iter() {
+        writeln!(trace_file, "{}", entry.format())?;
+    }
+
+    // breakpoints on outputs stop right after the output
+    let mut computer = Computer::from_config(computer_config);
+    let first_output_run = computer.run(
+        &program,
+        &RunOptions {
+            breakpoints: vec![Breakpoint::Output(None)],
+            cycle_limit: Some(100_000),
+            ..RunOptions::default()
+        },
+    )?;
+    writeln!(trace_file)?;
+    writeln!(
+        trace_file,
+        "The first output is {} after {} steps.",
+        first_output_run.output[0], first_output_run.num_steps
+    )?;
+
+    writeln!(trace_file)?;
+    writeln!(
+        trace_file,
+        "Registers whenever the program is at the start of its loop:"
+    )?;
+    let mut computer = Computer::from_config(computer_config);
+    let options = RunOptions {
+        breakpoints: vec![Breakpoint::InstructionPointer(0)],
+        cycle_limit: Some(100_000),
+        ..RunOptions::default()
+    };
+    while computer.run(&program, &options)?.breakpoint.is_some() {
+        writeln!(
+            trace_file,
+            "A={} B={} C={}",
+            computer.reg_a, computer.reg_b, computer.reg_c
+        )?;
+    }
 
-        output.len() == program.len()
+    // a program that never halts keeps what it did before reaching the cycle limit
+    let endless_program = [5, 4, 3, 0];
+    let mut computer = Computer::from_config("Register A: 1");
+    let error = computer
+        .run(
+            &endless_program,
+            &RunOptions {
+                trace: true,
+                cycle_limit: Some(6),
+                ..RunOptions::default()
+            },
+        )
+        .err()
+        .ok_or_else(|| Error::other("The endless program halted"))?;
+    let cycle_limit_reached = error
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<CycleLimitReached>())
+        .ok_or_else(|| Error::other("The endless program failed for another reason"))?;
+    writeln!(trace_file)?;
+    writeln!(
+        trace_file,
+        "The endless loop {} fails with: {}",
+        format_program(&endless_program),
+        cycle_limit_reached
+    )?;
+    for entry in cycle_limit_reached.partial_result.trace.iter() {
+        writeln!(trace_file, "{}", entry.format())?;
     }
+
+    Ok(())
 }
 
 pub fn run() -> Result<(), Error> {
     let _ = task1();
     let _ = task2();
+    let _ = write_disassembly();
+    let _ = write_trace();
+    let _ = write_assembler_examples();
 
     println!("Completed solutions for Day 17!");
 
@@ -207,49 +776,116 @@ fn task1() -> Result<(), Error> {
     Ok(())
 }
 
-fn _task2_general() -> Result<(), Error> {
-    println!("Computing solution for task 2 of Day 17...");
-    println!("NOTE / WARNING: This would work in theory (until overflow), but this takes way too long. The actual solution for the input needs to be obtained by analysing the program's actions and back-tracking the necessary register states.");
-
-    let input_data = fs::read_to_string("input_data/day17_input.txt")?;
-
-    let (computer_config, program_str) = input_data.split_once("\n\n").unwrap();
-    let program = program_from_str(program_str);
-    let computer = Computer::from_config(computer_config);
+/// Checks that the program is a single loop that outputs one value per iteration and shifts A
+/// right by a fixed amount, with B and C computed from A afresh in every iteration, so that
+/// each output only depends on the bits of A that are not yet shifted out. Returns the shift.
+fn analyse_quine_shape(program: &[u32]) -> Result<u32, Error> {
+    let wrong_shape = |reason: &str| {
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Program is not a single loop shifting A: {}", reason),
+        ))
+    };
+
+    let instructions = disassemble(program)?;
+    let Some((last, body)) = instructions.split_last() else {
+        return wrong_shape("the program is empty");
+    };
+    if last.opcode != 3 || last.operand != 0 {
+        return wrong_shape("it does not end with jnz 0");
+    }
+    if body.iter().any(|instruction| instruction.opcode == 3) {
+        return wrong_shape("it has more than one jump");
+    }
+    if body
+        .iter()
+        .filter(|instruction| instruction.opcode == 5)
+        .count()
+        != 1
+    {
+        return wrong_shape("it does not output exactly one value per iteration");
+    }
 
-    let mut reg_a_start_value = 0;
-    loop {
-        let mut test_computer = computer;
-        test_computer.reg_a = reg_a_start_value;
+    let shifts: Vec<&Instruction> = body
+        .iter()
+        .filter(|instruction| instruction.opcode == 0)
+        .collect();
+    if shifts.len() != 1 {
+        return wrong_shape("it does not have exactly one adv");
+    }
+    let shift = shifts[0].operand;
+    if shift == 0 || shift > 3 {
+        return wrong_shape("adv does not shift A by a literal amount");
+    }
+    if shift as usize * program.len() > 64 {
+        return wrong_shape("the start values would not fit into register A");
+    }
 
-        if test_computer._check_output_program_copy(program.clone()) {
-            break;
+    // B and C must not carry over from the previous iteration
+    let (mut b_written, mut c_written) = (false, false);
+    for instruction in body.iter() {
+        let reads_b = matches!(instruction.opcode, 1 | 4)
+            || (instruction.takes_combo_operand() && instruction.operand == 5);
+        let reads_c = instruction.opcode == 4
+            || (instruction.takes_combo_operand() && instruction.operand == 6);
+        if (reads_b && !b_written) || (reads_c && !c_written) {
+            return wrong_shape(&format!(
+                "{} {} at address {} depends on the previous iteration",
+                instruction.mnemonic(),
+                instruction.operand_str(),
+                instruction.address
+            ));
         }
-        reg_a_start_value += 1;
+        b_written |= matches!(instruction.opcode, 1 | 2 | 4 | 6);
+        c_written |= instruction.opcode == 7;
     }
 
-    let mut solution_file = fs::OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open("solutions/day17_solution.txt")?;
-    writeln!(solution_file)?;
-    writeln!(solution_file, "Solution for Task 2 of Day 17:")?;
-    writeln!(solution_file, "The smallest initial value of register A that causes the program to output a copy of itself is {}.", reg_a_start_value)?;
-
-    Ok(())
+    Ok(shift)
 }
 
-fn start_value_vector_to_u64(start_value_vec: &[u32]) -> u64 {
-    let mut start_value = 0;
-    for val in start_value_vec.iter() {
-        start_value += *val as u64;
-        start_value <<= 3;
+/// Finds all initial values of register A for which the program outputs a copy of itself, in
+/// ascending order. Builds the values from the most significant bits down, so that the outputs
+/// match the end of the program, backtracking when no bits fit.
+fn find_quine_start_values(computer: &Computer, program: &[u32]) -> Result<Vec<u64>, Error> {
+    let shift = analyse_quine_shape(program)?;
+    let options = RunOptions {
+        cycle_limit: Some(10 * program.len() * program.len()),
+        ..RunOptions::default()
+    };
+
+    let mut start_values = Vec::new();
+    // (value of the most significant bits, number of outputs they have to produce)
+    let mut to_check = vec![(0u64, 0usize)];
+    while let Some((prefix, num_outputs)) = to_check.pop() {
+        if num_outputs == program.len() {
+            start_values.push(prefix);
+            continue;
+        }
+        let expected_output = &program[program.len() - num_outputs - 1..];
+        for bits in 0..(1u64 << shift) {
+            let candidate = (prefix << shift) | bits;
+            if candidate == 0 {
+                continue;
+            }
+            let mut test_computer = *computer;
+            test_computer.reg_a = candidate;
+            let output = test_computer.run(program, &options)?.output;
+            if output
+                .iter()
+                .copied()
+                .eq(expected_output.iter().map(|&val| val as u64))
+            {
+                to_check.push((candidate, num_outputs + 1));
+            }
+        }
     }
-    start_value >> 3
+
+    start_values.sort_unstable();
+    Ok(start_values)
 }
+
 fn task2() -> Result<(), Error> {
     println!("Computing solution for task 2 of Day 17...");
-    println!("This solution makes use of the fact that for the given input program, the program loops, right-shifting the value in register A by 3 bits each iteration and outputting a single value, until register A reaches 0.");
 
     let input_data = fs::read_to_string("input_data/day17_input.txt")?;
 
@@ -257,33 +893,13 @@ fn task2() -> Result<(), Error> {
     let program = program_from_str(program_str);
     let computer = Computer::from_config(computer_config);
 
-    let mut reg_a_start_value_vec: Vec<u32> = Vec::new();
-
-    let mut initial_test_value = 0;
-    while reg_a_start_value_vec.len() < program.len() {
-        let mut success = false;
-        for test_bits in initial_test_value..8 {
-            reg_a_start_value_vec.push(test_bits);
-
-            let mut test_computer = computer;
-            test_computer.reg_a = start_value_vector_to_u64(&reg_a_start_value_vec);
-
-            if test_computer.get_first_program_output(program.clone())
-                == program[program.len() - reg_a_start_value_vec.len()] as u64
-            {
-                success = true;
-                break;
-            }
-
-            reg_a_start_value_vec.pop();
-        }
-        if !success {
-            // go back and continue looking
-            initial_test_value = reg_a_start_value_vec.pop().unwrap() + 1;
-        } else {
-            initial_test_value = 0;
-        }
-    }
+    let start_values = find_quine_start_values(&computer, &program)?;
+    let smallest_start_value = *start_values.first().ok_or_else(|| {
+        Error::new(
+            ErrorKind::NotFound,
+            "No initial value of register A makes the program output itself",
+        )
+    })?;
 
     let mut solution_file = fs::OpenOptions::new()
         .append(true)
@@ -291,7 +907,57 @@ fn task2() -> Result<(), Error> {
         .open("solutions/day17_solution.txt")?;
     writeln!(solution_file)?;
     writeln!(solution_file, "Solution for Task 2 of Day 17:")?;
-    writeln!(solution_file, "The smallest initial value of register A that causes the program to output a copy of itself is {}.", start_value_vector_to_u64(&reg_a_start_value_vec))?;
+    writeln!(solution_file, "The smallest initial value of register A that causes the program to output a copy of itself is {}.", smallest_start_value)?;
+    writeln!(
+        solution_file,
+        "All {} initial values that do so are {}.",
+        start_values.len(),
+        start_values
+            .iter()
+            .map(|start_value| start_value.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    )?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instruction_pointer_breakpoints() {
+        let program = program_from_str("Program: 0,1,5,4,3,0");
+        let mut computer = Computer::from_config("Register A: 729");
+        let options = RunOptions {
+            breakpoints: vec![Breakpoint::InstructionPointer(0)],
+            ..RunOptions::default()
+        };
+
+        // a fresh run stops before its first instruction, resuming runs one loop iteration
+        let result = computer.run(&program, &options).unwrap();
+        assert_eq!(
+            (result.num_steps, result.breakpoint),
+            (0, options.breakpoints.first().copied())
+        );
+        let result = computer.run(&program, &options).unwrap();
+        assert_eq!((result.num_steps, result.output), (3, vec![4]));
+    }
+
+    #[test]
+    fn cycle_limit_keeps_partial_result() {
+        let mut computer = Computer::from_config("Register A: 1");
+        let options = RunOptions {
+            cycle_limit: Some(5),
+            ..RunOptions::default()
+        };
+
+        let error = computer.run(&[5, 4, 3, 0], &options).unwrap_err();
+        let cycle_limit_reached = error
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<CycleLimitReached>())
+            .unwrap();
+        assert_eq!(cycle_limit_reached.partial_result.output, vec![1, 1, 1]);
+    }
+}
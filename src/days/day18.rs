@@ -3,8 +3,9 @@ use std::collections::BinaryHeap; // I want to only use the standard library
 use std::collections::{HashMap, HashSet};
 
 use std::fs;
-use std::io::Error;
 use std::io::Write;
+use std::io::{Error, ErrorKind};
+use std::time::Instant;
 
 #[derive(Eq, Hash, PartialEq, Clone, Copy)]
 struct Location {
@@ -30,6 +31,51 @@ impl PartialOrd for HeapEntry {
     }
 }
 
+/// Union-find over the cells of the memory space, used to track which free cells are connected.
+struct DisjointSets {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+}
+impl DisjointSets {
+    fn new(size: usize) -> DisjointSets {
+        DisjointSets {
+            parent: (0..size).collect(),
+            rank: vec![0; size],
+        }
+    }
+
+    fn find(&mut self, element: usize) -> usize {
+        let mut root = element;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        // path compression
+        let mut current = element;
+        while self.parent[current] != root {
+            let next = self.parent[current];
+            self.parent[current] = root;
+            current = next;
+        }
+        root
+    }
+
+    fn union(&mut self, first: usize, second: usize) {
+        let first_root = self.find(first);
+        let second_root = self.find(second);
+        if first_root == second_root {
+            return;
+        }
+        match self.rank[first_root].cmp(&self.rank[second_root]) {
+            Ordering::Less => self.parent[first_root] = second_root,
+            Ordering::Greater => self.parent[second_root] = first_root,
+            Ordering::Equal => {
+                self.parent[second_root] = first_root;
+                self.rank[first_root] += 1;
+            }
+        }
+    }
+}
+
 struct MemorySpace {
     height: u32,
     width: u32,
@@ -50,6 +96,130 @@ impl MemorySpace {
         }
     }
 
+    fn cell_index(&self, location: Location) -> usize {
+        (location.y * self.width + location.x) as usize
+    }
+
+    fn free_neighbours(&self, location: Location) -> Vec<Location> {
+        let mut neighbours = Vec::new();
+        if location.y > 0 {
+            neighbours.push(Location {
+                x: location.x,
+                y: location.y - 1,
+            });
+        }
+        if location.y < self.height - 1 {
+            neighbours.push(Location {
+                x: location.x,
+                y: location.y + 1,
+            });
+        }
+        if location.x > 0 {
+            neighbours.push(Location {
+                x: location.x - 1,
+                y: location.y,
+            });
+        }
+        if location.x < self.width - 1 {
+            neighbours.push(Location {
+                x: location.x + 1,
+                y: location.y,
+            });
+        }
+        neighbours.retain(|neighbour| !self.corrupted_pos.contains(neighbour));
+        neighbours
+    }
+
+    /// Finds the first of the falling bytes after which the end can no longer be reached from the
+    /// start, or None if it stays reachable. Lets all bytes fall, then removes them again in
+    /// reverse order, joining up the free cells until start and end are connected.
+    fn first_blocking_byte(
+        &self,
+        falling_bytes: &[Location],
+        start_pos: Location,
+        end_pos: Location,
+    ) -> Option<Location> {
+        // without a path to begin with, no byte can be the one blocking it
+        if self.corrupted_pos.contains(&start_pos) || self.corrupted_pos.contains(&end_pos) {
+            return None;
+        }
+        let mut final_space = MemorySpace {
+            height: self.height,
+            width: self.width,
+            corrupted_pos: self.corrupted_pos.clone(),
+        };
+        // a byte falling onto an already corrupted cell changes nothing
+        let mut first_fall: HashMap<Location, usize> = HashMap::new();
+        for (index, &byte) in falling_bytes.iter().enumerate() {
+            if byte.x < self.width && byte.y < self.height && !self.corrupted_pos.contains(&byte) {
+                first_fall.entry(byte).or_insert(index);
+                final_space.corrupted_pos.insert(byte);
+            }
+        }
+
+        let mut connected_cells = DisjointSets::new((self.width * self.height) as usize);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let location = Location { x, y };
+                if final_space.corrupted_pos.contains(&location) {
+                    continue;
+                }
+                for neighbour in final_space.free_neighbours(location) {
+                    connected_cells.union(
+                        final_space.cell_index(location),
+                        final_space.cell_index(neighbour),
+                    );
+                }
+            }
+        }
+        let is_connected = |connected_cells: &mut DisjointSets, space: &MemorySpace| {
+            !space.corrupted_pos.contains(&start_pos)
+                && !space.corrupted_pos.contains(&end_pos)
+                && connected_cells.find(space.cell_index(start_pos))
+                    == connected_cells.find(space.cell_index(end_pos))
+        };
+        if is_connected(&mut connected_cells, &final_space) {
+            return None;
+        }
+
+        for (index, &byte) in falling_bytes.iter().enumerate().rev() {
+            if first_fall.get(&byte) != Some(&index) {
+                continue;
+            }
+            final_space.corrupted_pos.remove(&byte);
+            for neighbour in final_space.free_neighbours(byte) {
+                connected_cells.union(
+                    final_space.cell_index(byte),
+                    final_space.cell_index(neighbour),
+                );
+            }
+            if is_connected(&mut connected_cells, &final_space) {
+                return Some(byte);
+            }
+        }
+
+        None
+    }
+
+    /// Finds the same byte as `first_blocking_byte`, by letting the bytes fall one at a time and
+    /// searching for a path after each one.
+    fn first_blocking_byte_by_path_search(
+        &mut self,
+        falling_bytes: &[Location],
+        start_pos: Location,
+        end_pos: Location,
+    ) -> Option<Location> {
+        for &byte in falling_bytes.iter() {
+            self.corrupt_position(byte.x, byte.y);
+
+            let (reachable, _) = self.shortest_path_length(start_pos, end_pos);
+            if !reachable {
+                return Some(byte);
+            }
+        }
+        None
+    }
+
     /// Dijkstra's algorithm
     fn shortest_path_length(&self, start_pos: Location, end_pos: Location) -> (bool, u32) {
         let mut priority_queue: BinaryHeap<HeapEntry> = BinaryHeap::new();
@@ -136,67 +306,214 @@ impl MemorySpace {
     }
 }
 
-pub fn run() -> Result<(), Error> {
-    let _ = task1();
-    let _ = task2();
-
-    println!("Completed solutions for Day 18!");
+fn parse_bytes(input: &str) -> Vec<Location> {
+    input
+        .trim()
+        .lines()
+        .map(|corrupted_loc_str| {
+            let (corrupted_x_str, corrupted_y_str) = corrupted_loc_str.split_once(',').unwrap();
+            Location {
+                x: corrupted_x_str.parse().unwrap(),
+                y: corrupted_y_str.parse().unwrap(),
+            }
+        })
+        .collect()
+}
 
-    Ok(())
+/// The size of the memory space, how many bytes fall for the first task, and where the path
+/// starts and ends.
+#[derive(Clone, Copy)]
+struct MemoryConfig {
+    width: u32,
+    height: u32,
+    num_fallen_bytes: usize,
+    start_pos: Location,
+    end_pos: Location,
 }
+impl MemoryConfig {
+    /// Picks the layout of the puzzle the bytes belong to: the example in the puzzle description
+    /// lets 12 bytes fall onto a 7x7 grid, the real input 1024 onto a 71x71 grid. This is a
+    /// heuristic based on the largest coordinate, so bytes that fit neither get a grid just large
+    /// enough to hold them and the real input's byte count. The path goes from corner to corner.
+    fn detect(falling_bytes: &[Location]) -> MemoryConfig {
+        let max_x = falling_bytes.iter().map(|byte| byte.x).max().unwrap_or(0);
+        let max_y = falling_bytes.iter().map(|byte| byte.y).max().unwrap_or(0);
+        let (width, height, num_fallen_bytes) = if max_x < 7 && max_y < 7 {
+            (7, 7, 12)
+        } else if max_x < 71 && max_y < 71 {
+            (71, 71, 1024)
+        } else {
+            (max_x + 1, max_y + 1, 1024)
+        };
+        MemoryConfig {
+            width,
+            height,
+            num_fallen_bytes,
+            start_pos: Location { x: 0, y: 0 },
+            end_pos: Location {
+                x: width - 1,
+                y: height - 1,
+            },
+        }
+    }
 
-fn task1() -> Result<(), Error> {
-    println!("Computing solution for task 1 of Day 18...");
+    /// Overrides the detected defaults with command line options like `--grid=7x7`, `--bytes=12`,
+    /// `--start=0,0` and `--end=6,6`.
+    fn from_args(args: &[String], falling_bytes: &[Location]) -> Result<MemoryConfig, Error> {
+        let mut config = MemoryConfig::detect(falling_bytes);
+        let invalid_option = |arg: &str| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid option for Day 18: {}", arg),
+            )
+        };
 
-    let input_data = fs::read_to_string("input_data/day18_input.txt")?;
+        let mut end_pos = None;
+        for arg in args.iter() {
+            if let Some(grid_str) = arg.strip_prefix("--grid=") {
+                let (width_str, height_str) = grid_str
+                    .split_once('x')
+                    .ok_or_else(|| invalid_option(arg))?;
+                config.width = width_str.parse().map_err(|_| invalid_option(arg))?;
+                config.height = height_str.parse().map_err(|_| invalid_option(arg))?;
+                if config.width == 0 || config.height == 0 {
+                    return Err(invalid_option(arg));
+                }
+            } else if let Some(num_bytes_str) = arg.strip_prefix("--bytes=") {
+                config.num_fallen_bytes = num_bytes_str.parse().map_err(|_| invalid_option(arg))?;
+            } else if let Some(start_str) = arg.strip_prefix("--start=") {
+                config.start_pos = parse_location(start_str).ok_or_else(|| invalid_option(arg))?;
+            } else if let Some(end_str) = arg.strip_prefix("--end=") {
+                end_pos = Some(parse_location(end_str).ok_or_else(|| invalid_option(arg))?);
+            } else if arg.starts_with("--") && !arg.starts_with("--input=") && arg != "--bench" {
+                return Err(invalid_option(arg));
+            }
+        }
+        // the end defaults to the far corner of the grid, whatever order the options come in
+        config.end_pos = end_pos.unwrap_or(Location {
+            x: config.width - 1,
+            y: config.height - 1,
+        });
 
-    let mut memory_space = MemorySpace::new(71, 71);
-    for (i, corrupted_loc_str) in input_data.trim().lines().enumerate() {
-        if i >= 1024 {
-            break;
+        if [config.start_pos, config.end_pos]
+            .iter()
+            .any(|location| location.x >= config.width || location.y >= config.height)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Start and end must lie inside the memory space",
+            ));
         }
-        let (corrupted_x_str, corrupted_y_str) = corrupted_loc_str.split_once(',').unwrap();
-        let corrupted_x: u32 = corrupted_x_str.parse().unwrap();
-        let corrupted_y: u32 = corrupted_y_str.parse().unwrap();
 
-        memory_space.corrupt_position(corrupted_x, corrupted_y);
+        Ok(config)
     }
+}
 
-    let (_, shortest_path_length) =
-        memory_space.shortest_path_length(Location { x: 0, y: 0 }, Location { x: 70, y: 70 });
+fn parse_location(location_str: &str) -> Option<Location> {
+    let (x_str, y_str) = location_str.split_once(',')?;
+    Some(Location {
+        x: x_str.trim().parse().ok()?,
+        y: y_str.trim().parse().ok()?,
+    })
+}
 
-    let mut solution_file = fs::File::create("solutions/day18_solution.txt")?;
-    writeln!(solution_file, "Solution for Task 1 of Day 18:")?;
-    writeln!(solution_file, "After 1024 bytes have fallen, the shortest path from (0, 0) to (70, 70) avoiding corrupted memory locations has length {}.", shortest_path_length)?;
+fn shortest_path_after_fall(falling_bytes: &[Location], config: &MemoryConfig) -> Option<u32> {
+    let mut memory_space = MemorySpace::new(config.height, config.width);
+    for byte in falling_bytes.iter().take(config.num_fallen_bytes) {
+        memory_space.corrupt_position(byte.x, byte.y);
+    }
 
-    Ok(())
+    let (reachable, shortest_path_length) =
+        memory_space.shortest_path_length(config.start_pos, config.end_pos);
+    reachable.then_some(shortest_path_length)
 }
 
-fn task2() -> Result<(), Error> {
-    println!("Computing solution for task 2 of Day 18...");
-    let input_data = fs::read_to_string("input_data/day18_input.txt")?;
+fn find_first_cut_off_byte(falling_bytes: &[Location], config: &MemoryConfig) -> Option<Location> {
+    MemorySpace::new(config.height, config.width).first_blocking_byte(
+        falling_bytes,
+        config.start_pos,
+        config.end_pos,
+    )
+}
 
-    let mut memory_space = MemorySpace::new(71, 71);
-    let mut first_cut_off_byte = Location { x: 0, y: 0 };
-    // It would be faster to compute the fastest path, then recompute it only once a block falls on that path (depending on the pattern in which the blocks fall it may or may not be faster than binary-searching for the block), but just iterating is very simple to implement and fast enough
-    for corrupted_loc_str in input_data.trim().lines() {
-        let (corrupted_x_str, corrupted_y_str) = corrupted_loc_str.split_once(',').unwrap();
-        let corrupted_x: u32 = corrupted_x_str.parse().unwrap();
-        let corrupted_y: u32 = corrupted_y_str.parse().unwrap();
+/// Times the union-find approach against searching for a path after every byte.
+fn benchmark_blocking_byte_search(
+    falling_bytes: &[Location],
+    config: &MemoryConfig,
+) -> Result<(), Error> {
+    println!("Benchmarking the search for the first blocking byte for Day 18...");
 
-        memory_space.corrupt_position(corrupted_x, corrupted_y);
+    let start_time = Instant::now();
+    let blocking_byte = find_first_cut_off_byte(falling_bytes, config);
+    let union_find_time = start_time.elapsed();
 
-        let (reachable, _) =
-            memory_space.shortest_path_length(Location { x: 0, y: 0 }, Location { x: 70, y: 70 });
+    let start_time = Instant::now();
+    let blocking_byte_by_path_search = MemorySpace::new(config.height, config.width)
+        .first_blocking_byte_by_path_search(falling_bytes, config.start_pos, config.end_pos);
+    let path_search_time = start_time.elapsed();
 
-        if !reachable {
-            first_cut_off_byte = Location {
-                x: corrupted_x,
-                y: corrupted_y,
-            };
-            break;
-        }
+    if blocking_byte != blocking_byte_by_path_search {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "The two searches found different blocking bytes",
+        ));
     }
+    println!("  union-find in reverse: {:.2?}", union_find_time);
+    println!("  path search after every byte: {:.2?}", path_search_time);
+
+    Ok(())
+}
+
+/// Besides the options read by `MemoryConfig::from_args`, `--input=<path>` reads the falling
+/// bytes from another file, e.g. the example from the puzzle description, and `--bench` times the
+/// search for the first blocking byte against the slow path search.
+pub fn run() -> Result<(), Error> {
+    let args: Vec<String> = std::env::args().collect();
+    let input_path = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--input="))
+        .unwrap_or("input_data/day18_input.txt");
+    let falling_bytes = parse_bytes(&fs::read_to_string(input_path)?);
+    let config = MemoryConfig::from_args(&args, &falling_bytes)?;
+
+    let _ = task1(&falling_bytes, &config);
+    let _ = task2(&falling_bytes, &config);
+    if args.iter().any(|arg| arg == "--bench") {
+        let _ = benchmark_blocking_byte_search(&falling_bytes, &config);
+    }
+
+    println!("Completed solutions for Day 18!");
+
+    Ok(())
+}
+
+fn task1(falling_bytes: &[Location], config: &MemoryConfig) -> Result<(), Error> {
+    println!("Computing solution for task 1 of Day 18...");
+
+    let shortest_path_length = shortest_path_after_fall(falling_bytes, config)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "The exit cannot be reached"))?;
+
+    let mut solution_file = fs::File::create("solutions/day18_solution.txt")?;
+    writeln!(solution_file, "Solution for Task 1 of Day 18:")?;
+    writeln!(
+        solution_file,
+        "After {} bytes have fallen, the shortest path from ({}, {}) to ({}, {}) avoiding corrupted memory locations has length {}.",
+        config.num_fallen_bytes,
+        config.start_pos.x,
+        config.start_pos.y,
+        config.end_pos.x,
+        config.end_pos.y,
+        shortest_path_length
+    )?;
+
+    Ok(())
+}
+
+fn task2(falling_bytes: &[Location], config: &MemoryConfig) -> Result<(), Error> {
+    println!("Computing solution for task 2 of Day 18...");
+
+    let first_cut_off_byte = find_first_cut_off_byte(falling_bytes, config)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "No byte cuts off the path to the exit"))?;
 
     let mut solution_file = fs::OpenOptions::new()
         .append(true)
@@ -212,3 +529,47 @@ fn task2() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "5,4\n4,2\n4,5\n3,0\n2,1\n6,3\n2,4\n1,5\n0,6\n3,3\n2,6\n5,1\n1,2\n5,5\n2,5\n6,5\n1,4\n0,4\n6,4\n1,1\n6,1\n1,0\n0,5\n1,6\n2,0";
+
+    #[test]
+    fn example() {
+        let falling_bytes = parse_bytes(EXAMPLE);
+        let config = MemoryConfig::detect(&falling_bytes);
+        assert_eq!((config.width, config.height), (7, 7));
+        assert_eq!(config.num_fallen_bytes, 12);
+
+        assert_eq!(shortest_path_after_fall(&falling_bytes, &config), Some(22));
+        assert!(find_first_cut_off_byte(&falling_bytes, &config) == Some(Location { x: 6, y: 1 }));
+    }
+
+    #[test]
+    fn invalid_options() {
+        let falling_bytes = parse_bytes(EXAMPLE);
+        for arg in ["--grid=7", "--bytes=many", "--end=7,7", "--unknown"] {
+            let args = vec!["18".to_string(), arg.to_string()];
+            assert!(MemoryConfig::from_args(&args, &falling_bytes).is_err());
+        }
+
+        let args = vec![
+            "18".to_string(),
+            "--grid=10x8".to_string(),
+            "--bytes=5".to_string(),
+        ];
+        let config = MemoryConfig::from_args(&args, &falling_bytes).unwrap();
+        assert_eq!((config.width, config.height), (10, 8));
+        assert_eq!(config.num_fallen_bytes, 5);
+        assert!(config.end_pos == Location { x: 9, y: 7 });
+
+        for args in [["--end=3,3", "--grid=10x10"], ["--grid=10x10", "--end=3,3"]] {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            let config = MemoryConfig::from_args(&args, &falling_bytes).unwrap();
+            assert_eq!((config.width, config.height), (10, 10));
+            assert!(config.end_pos == Location { x: 3, y: 3 });
+        }
+    }
+}
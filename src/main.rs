@@ -43,8 +43,9 @@ fn main() {
         return;
     }
 
+    let mut any_failed = false;
     for day in days_to_run {
-        let _ = match day {
+        let result = match day {
             1 => day01::run(),
             2 => day02::run(),
             3 => day03::run(),
@@ -76,5 +77,13 @@ fn main() {
                 format!("Day {} not implemented", day),
             )),
         };
+        if let Err(error) = result {
+            eprintln!("Day {} failed: {}", day, error);
+            any_failed = true;
+        }
+    }
+
+    if any_failed {
+        std::process::exit(1);
     }
 }